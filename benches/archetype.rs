@@ -0,0 +1,93 @@
+//! Compares a three way BTreeMap join, like the joins in tests/src/full_tests.rs, against querying the same
+//! components from Archetypes, where they are stored in columns next to each other. Run with cargo bench --bench archetype
+#[macro_use] extern crate genecs;
+
+use genecs::component::Component;
+use genecs::entity::{Entity, EntityID};
+use genecs::join::Join;
+use genecs::storage::Archetypes;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+const ENTITIES : usize = 100_000;
+const TICKS : usize = 100;
+
+#[derive(Clone, Copy)]
+struct Position(f32, f32);
+#[derive(Clone, Copy)]
+struct Velocity(f32, f32);
+#[derive(Clone, Copy)]
+struct Mass(f32);
+
+impl_component!(Position, BTreeMap<EntityID, Position>);
+impl_component!(Velocity, BTreeMap<EntityID, Velocity>);
+impl_component!(Mass, BTreeMap<EntityID, Mass>);
+
+/// Every entity has a Position, half have a Velocity and a third of those have a Mass
+fn components(i : usize) -> (Position, Option<Velocity>, Option<Mass>) {
+    let velocity = if i % 2 == 0 { Some(Velocity(1.0, 0.5)) } else { None };
+    let mass = if i % 3 == 0 { Some(Mass(2.0)) } else { None };
+    (Position(i as f32, 0.0), velocity, mass)
+}
+
+fn run_btree_map() -> Duration {
+    let mut guard = acquire!(Write(Position), Write(Velocity), Write(Mass));
+    let (positions, velocities, masses) = guard.get();
+    for i in 0..ENTITIES {
+        let (position, velocity, mass) = components(i);
+        let mut entity = Entity::new().add(*positions, position);
+        if let Some(velocity) = velocity {
+            entity = entity.add(*velocities, velocity);
+        }
+        if let Some(mass) = mass {
+            entity.add(*masses, mass);
+        }
+    }
+    let now = Instant::now();
+    for _ in 0..TICKS {
+        for (_, (position, (velocity, mass))) in (&mut **positions, (&**velocities, &**masses)).join() {
+            position.0 += velocity.0 / mass.0;
+            position.1 += velocity.1 / mass.0;
+        }
+    }
+    now.elapsed()
+}
+
+fn run_archetypes(chunked : bool) -> Duration {
+    let mut archetypes = Archetypes::new();
+    for i in 0..ENTITIES {
+        let id = Entity::new().get_id();
+        let (position, velocity, mass) = components(i);
+        archetypes.insert(id, position);
+        if let Some(velocity) = velocity {
+            archetypes.insert(id, velocity);
+        }
+        if let Some(mass) = mass {
+            archetypes.insert(id, mass);
+        }
+    }
+    let now = Instant::now();
+    for _ in 0..TICKS {
+        if chunked {
+            for (_, (positions, velocities, masses)) in archetypes.chunks::<(&mut Position, &Velocity, &Mass)>() {
+                for ((position, velocity), mass) in positions.iter_mut().zip(velocities).zip(masses) {
+                    position.0 += velocity.0 / mass.0;
+                    position.1 += velocity.1 / mass.0;
+                }
+            }
+        } else {
+            for (_, (position, velocity, mass)) in archetypes.query::<(&mut Position, &Velocity, &Mass)>() {
+                position.0 += velocity.0 / mass.0;
+                position.1 += velocity.1 / mass.0;
+            }
+        }
+    }
+    now.elapsed()
+}
+
+fn main() {
+    println!("Position, Velocity, Mass join ({} entities x {} ticks)", ENTITIES, TICKS);
+    println!("    BTreeMap join:      {:>8.2?}", run_btree_map());
+    println!("    Archetypes query:   {:>8.2?}", run_archetypes(false));
+    println!("    Archetypes chunks:  {:>8.2?}", run_archetypes(true));
+}
@@ -0,0 +1,87 @@
+//! Compares dispatch_parallel! on the persistent ThreadPool against spawning a scoped thread per
+//! system on every dispatch, which is what dispatch_parallel! previously did. Run with cargo bench --bench dispatch
+#[macro_use] extern crate genecs;
+
+use genecs::resource::Resource;
+use genecs::static_storage::Stored;
+use genecs::system::{System, Write, Data};
+use genecs::thread_pool::ThreadPool;
+use std::time::{Duration, Instant};
+
+const SYSTEMS : usize = 12;
+const TICKS : usize = 1_000;
+
+/// A small system that increments it's own resource, so the systems never wait on each other
+trait Counter {
+    fn bump(&mut self);
+}
+
+struct Bump<T>(std::marker::PhantomData<T>);
+
+impl<T : Resource + Stored<Storage = T> + Counter> System for Bump<T> {
+    type SystemData = Write<T>;
+    fn run(&mut self, counter : Data<Self>) {
+        counter.bump();
+    }
+}
+
+macro_rules! counters {
+    ($($name:ident),*) => {
+        $(
+            struct $name(u64);
+            impl_resource!($name, $name(0));
+            impl Counter for $name {
+                fn bump(&mut self) { self.0 += 1; }
+            }
+        )*
+        /// Creates one system per counter
+        fn systems() -> ($(Bump<$name>,)*) {
+            ($(Bump::<$name>(std::marker::PhantomData),)*)
+        }
+    };
+}
+
+counters!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11);
+
+fn run_spawned() -> Duration {
+    let (mut s0, mut s1, mut s2, mut s3, mut s4, mut s5, mut s6, mut s7, mut s8, mut s9, mut s10, mut s11) = systems();
+    let now = Instant::now();
+    for _ in 0..TICKS {
+        crossbeam_utils::thread::scope(|s| {
+            s.spawn(|_| s0.dispatch());
+            s.spawn(|_| s1.dispatch());
+            s.spawn(|_| s2.dispatch());
+            s.spawn(|_| s3.dispatch());
+            s.spawn(|_| s4.dispatch());
+            s.spawn(|_| s5.dispatch());
+            s.spawn(|_| s6.dispatch());
+            s.spawn(|_| s7.dispatch());
+            s.spawn(|_| s8.dispatch());
+            s.spawn(|_| s9.dispatch());
+            s.spawn(|_| s10.dispatch());
+            s.spawn(|_| s11.dispatch());
+        }).unwrap();
+    }
+    now.elapsed()
+}
+
+fn run_pool(pool : &ThreadPool) -> Duration {
+    let (mut s0, mut s1, mut s2, mut s3, mut s4, mut s5, mut s6, mut s7, mut s8, mut s9, mut s10, mut s11) = systems();
+    let now = Instant::now();
+    for _ in 0..TICKS {
+        dispatch_parallel!(pool => &mut s0, &mut s1, &mut s2, &mut s3, &mut s4, &mut s5, &mut s6, &mut s7, &mut s8, &mut s9, &mut s10, &mut s11);
+    }
+    now.elapsed()
+}
+
+fn main() {
+    let spawned = run_spawned();
+    println!("dispatch_parallel! ({} systems x {} ticks)", SYSTEMS, TICKS);
+    println!("    thread per system:        {:>8.2?}", spawned);
+    for threads in [1, 4, 8] {
+        let pool = ThreadPool::new(threads);
+        // warm up the pool
+        run_pool(&pool);
+        println!("    thread pool ({} threads):  {:>8.2?}", threads, run_pool(&pool));
+    }
+}
@@ -0,0 +1,145 @@
+//! Compares the per storage StorageLocks used by acquire! against the previous design, where every
+//! storage was guarded by one process wide mutex and condition variable, and every release woke up
+//! every waiting thread. Run with cargo bench --bench locking
+#[macro_use] extern crate genecs;
+
+use genecs::static_storage::Access;
+use genecs::resource::Resource;
+use std::sync::{Mutex, Condvar};
+use std::time::{Duration, Instant};
+use std::thread;
+
+const THREADS : usize = 8;
+const ITERATIONS : u64 = 20_000;
+/// The index of the Shared storage in the global lock
+const SHARED : usize = THREADS;
+
+/// The previous design of acquire!, the number of readers and writers of every storage
+/// is kept behind a single mutex, and every release notifies every waiting thread
+mod global {
+    use super::*;
+
+    static STORAGES : Mutex<[(u64, u64); super::THREADS + 1]> = Mutex::new([(0, 0); super::THREADS + 1]);
+    static CONDVAR : Condvar = Condvar::new();
+
+    pub fn acquire(requests : &[(usize, Access)]) {
+        let mut storages = STORAGES.lock().unwrap();
+        loop {
+            let available = requests.iter().all(|(i, access)| {
+                matches!((access, storages[*i]), (Access::Read, (_, 0)) | (Access::Write, (0, 0)))
+            });
+            if available {
+                break;
+            }
+            storages = CONDVAR.wait(storages).unwrap();
+        }
+        for (i, access) in requests {
+            match access {
+                Access::Read => storages[*i].0 += 1,
+                Access::Write => storages[*i].1 = 1,
+            }
+        }
+    }
+
+    pub fn release(requests : &[(usize, Access)]) {
+        let mut storages = STORAGES.lock().unwrap();
+        for (i, access) in requests {
+            match access {
+                Access::Read => storages[*i].0 -= 1,
+                Access::Write => storages[*i].1 = 0,
+            }
+        }
+        CONDVAR.notify_all();
+    }
+}
+
+/// A counter that is incremented by the benchmarks
+trait Counter {
+    fn bump(&mut self, by : u64);
+}
+
+macro_rules! counters {
+    ($($name:ident),*) => {
+        $(
+            struct $name(u64);
+            impl_resource!($name, $name(0));
+            impl Counter for $name {
+                fn bump(&mut self, by : u64) { self.0 += by; }
+            }
+        )*
+    };
+}
+
+counters!(C0, C1, C2, C3, C4, C5, C6, C7, Shared);
+
+/// Increments the counter of T using the value of Shared with acquire!
+fn per_storage<T : Resource + Counter>(write_shared : bool) {
+    for _ in 0..ITERATIONS {
+        if write_shared {
+            let mut guard = acquire!(Write(T), Write(Shared));
+            let (counter, shared) = guard.get();
+            shared.0 += 1;
+            counter.bump(shared.0 % 2);
+        } else {
+            let mut guard = acquire!(Write(T), Read(Shared));
+            let (counter, shared) = guard.get();
+            counter.bump(shared.0 + 1);
+        }
+    }
+}
+
+/// Does the same work as per_storage with the global lock
+fn global_lock(index : usize, write_shared : bool, counter : &Mutex<u64>, shared : &Mutex<u64>) {
+    let requests = [(index, Access::Write), (SHARED, if write_shared { Access::Write } else { Access::Read })];
+    for _ in 0..ITERATIONS {
+        global::acquire(&requests);
+        // The global lock guarantees exclusive access, the mutexes are uncontended and only used to hold the values
+        let mut shared = shared.lock().unwrap();
+        if write_shared {
+            *shared += 1;
+        }
+        *counter.lock().unwrap() += if write_shared { *shared % 2 } else { *shared + 1 };
+        drop(shared);
+        global::release(&requests);
+    }
+}
+
+fn run_per_storage(write_shared : bool) -> Duration {
+    let now = Instant::now();
+    thread::scope(|s| {
+        s.spawn(move || per_storage::<C0>(write_shared));
+        s.spawn(move || per_storage::<C1>(write_shared));
+        s.spawn(move || per_storage::<C2>(write_shared));
+        s.spawn(move || per_storage::<C3>(write_shared));
+        s.spawn(move || per_storage::<C4>(write_shared));
+        s.spawn(move || per_storage::<C5>(write_shared));
+        s.spawn(move || per_storage::<C6>(write_shared));
+        s.spawn(move || per_storage::<C7>(write_shared));
+    });
+    now.elapsed()
+}
+
+fn run_global(write_shared : bool) -> Duration {
+    let counters : Vec<Mutex<u64>> = (0..THREADS).map(|_| Mutex::new(0)).collect();
+    let shared = Mutex::new(0);
+    let now = Instant::now();
+    thread::scope(|s| {
+        for (i, counter) in counters.iter().enumerate() {
+            let shared = &shared;
+            s.spawn(move || global_lock(i, write_shared, counter, shared));
+        }
+    });
+    now.elapsed()
+}
+
+fn main() {
+    // Make sure every storage is initialized before timing
+    run_per_storage(false);
+    for (name, write_shared) in [("disjoint writes, shared read", false), ("disjoint writes, shared write", true)] {
+        let global = run_global(write_shared);
+        let per_storage = run_per_storage(write_shared);
+        println!("{} ({} threads x {} acquires)", name, THREADS, ITERATIONS);
+        println!("    global mutex:      {:>8.2?}", global);
+        println!("    per storage locks: {:>8.2?}", per_storage);
+    }
+}
@@ -0,0 +1,178 @@
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use crate::component::{Component, ComponentStorage};
+use crate::entity::{Entity, EntityID, EntityRegister};
+use crate::resource::Resource;
+use crate::world::World;
+
+/// A Command is a deferred change that is run when the Commands are applied
+pub type Command = Box<dyn FnOnce() + Send>;
+
+/// Commands is a queue of structural changes, spawning entities, adding and removing components and despawning entities,
+/// that are run later at a sync point. This lets a system add a component while iterating other storages without
+/// holding write access to every storage it might touch. Commands is a resource and recording only needs read access,
+/// so systems that run in parallel can all use Read<Commands> and record into the same queue.
+///
+/// Each Dispatcher has it's own queue, the commands recorded into the Commands resource by the systems of a Dispatcher
+/// go into the queue of that Dispatcher, which applies them at the end of every stage. So one Dispatcher never applies
+/// the commands of another. When using dispatch! or dispatch_parallel! the commands go into the Commands resource,
+/// call Commands::apply_queued once the systems have finished. Applying the commands acquires each storage that is
+/// changed, so it must not be done while holding a guard to one of them.
+///
+/// The Commands of a World are created with Commands::with_world, their ids are allocated by the World and their
+/// changes are made to the storages of the World. The Commands resource of a World, from acquire!(world => Read(Commands)),
+/// doesn't know which World it is in, so recording into it panics instead of changing the static storages.
+pub struct Commands {
+    queue: Mutex<Vec<Command>>,
+    world: Option<Arc<World>>,
+    /// true for the Commands resource, which records into the queue of the Dispatcher running the system
+    shared: bool,
+    /// true for the Commands resource of a World, which can't be recorded into
+    detached: bool,
+}
+crate::impl_resource!(Commands, Commands { queue: Mutex::new(Vec::new()), world: None, shared: true, detached: false });
+
+thread_local! {
+    /// The Commands that the Commands resource records into on this thread, this is set while a Dispatcher runs a system
+    static RECORDING : Cell<*const Commands> = const { Cell::new(std::ptr::null()) };
+}
+
+/// Restores the Commands that were being recorded into when a call to record returns or panics
+struct Restore(*const Commands);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        RECORDING.with(|recording| recording.set(self.0));
+    }
+}
+
+impl Commands {
+    /// Creates an empty queue of Commands
+    pub fn new() -> Self {
+        Commands { queue: Mutex::new(Vec::new()), world: None, shared: false, detached: false }
+    }
+
+    /// Creates an empty queue of Commands that changes the World instead of the static storages
+    pub fn with_world(world : Arc<World>) -> Self {
+        Commands { queue: Mutex::new(Vec::new()), world: Some(world), shared: false, detached: false }
+    }
+
+    /// Queues a function to be run when the Commands are applied, this panics for the Commands resource of a World
+    pub fn push<F : FnOnce() + Send + 'static>(&self, command : F) {
+        self.check_attached();
+        let recording = if self.shared { RECORDING.with(|recording| recording.get()) } else { std::ptr::null() };
+        // The Commands being recorded into outlive the call to record that set them
+        let target = if recording.is_null() { self } else { unsafe { &*recording } };
+        target.queue.lock().expect("Failed to access the Commands").push(Box::new(command));
+    }
+
+    /// Creates a new entity, the id is created immediately so that components can be added to it
+    /// with the Commands, but the components are only added once the Commands are applied
+    pub fn spawn(&self) -> EntityID {
+        self.check_attached();
+        match &self.world {
+            Some(world) => world.create_entity(),
+            None => EntityRegister::get_new_id(),
+        }
+    }
+
+    /// Queues adding the component to the entity, this will acquire the storage of C for writing
+    pub fn add<C, S>(&self, id : EntityID, comp : C) where C : Component<S> + Send + 'static, S : ComponentStorage<C, EntityID=EntityID> + Send + Sync + 'static {
+        match self.world.clone() {
+            Some(world) => self.push(move || {
+                let mut guard = acquire!(world => Write(C));
+                let (storage,) = guard.get();
+                Entity::from(id).add(*storage, comp);
+            }),
+            None => self.push(move || {
+                let mut guard = acquire!(Write(C));
+                let (storage,) = guard.get();
+                Entity::from(id).add(*storage, comp);
+            }),
+        }
+    }
+
+    /// Queues removing the component C from the entity, this will acquire the storage of C for writing
+    pub fn remove<C, S>(&self, id : EntityID) where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + Send + Sync + 'static {
+        match self.world.clone() {
+            Some(world) => self.push(move || {
+                let mut guard = acquire!(world => Write(C));
+                let (storage,) = guard.get();
+                Entity::from(id).rm::<C, S>(*storage);
+            }),
+            None => self.push(move || {
+                let mut guard = acquire!(Write(C));
+                let (storage,) = guard.get();
+                Entity::from(id).rm::<C, S>(*storage);
+            }),
+        }
+    }
+
+    /// Queues despawning the entity, see EntityRegister::despawn and World::despawn
+    pub fn despawn(&self, id : EntityID) {
+        match self.world.clone() {
+            Some(world) => self.push(move || {
+                world.despawn(id);
+            }),
+            None => self.push(move || {
+                EntityRegister::despawn(id);
+            }),
+        }
+    }
+
+    /// Returns the number of queued commands
+    pub fn len(&self) -> usize {
+        self.queue.lock().expect("Failed to access the Commands").len()
+    }
+
+    /// Returns true if there are no queued commands
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs the queued commands in the order they were recorded. Commands that are recorded
+    /// while applying are left in the queue for the next time the Commands are applied.
+    pub fn apply(&self) {
+        let queue = std::mem::take(&mut *self.queue.lock().expect("Failed to access the Commands"));
+        for command in queue {
+            command();
+        }
+    }
+
+    /// Marks the Commands resource of a World, which would otherwise change the static storages
+    pub(crate) fn detach(&mut self) {
+        self.shared = false;
+        self.detached = true;
+    }
+
+    /// Panics if the Commands are the Commands resource of a World
+    fn check_attached(&self) {
+        assert!(!self.detached, "The Commands resource of a World doesn't know the World, use Commands::with_world instead");
+    }
+
+    /// Runs the function with the Commands resource recording into these Commands on this thread, this is used by
+    /// the Dispatcher so that the commands of it's systems go into it's own queue
+    pub(crate) fn record<R>(&self, f : impl FnOnce() -> R) -> R {
+        let _restore = Restore(RECORDING.with(|recording| recording.replace(self)));
+        f()
+    }
+
+    /// Applies the commands queued in the Commands resource. The resource is only held while taking the
+    /// queue, so the commands can themselves record more commands into it.
+    pub fn apply_queued() {
+        let queue = {
+            let mut guard = acquire!(Read(Commands));
+            let (commands,) = guard.get();
+            std::mem::take(&mut *commands.queue.lock().expect("Failed to access the Commands"))
+        };
+        for command in queue {
+            command();
+        }
+    }
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
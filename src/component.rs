@@ -1,4 +1,10 @@
 use crate::static_storage::StaticStorage;
+use crate::entity::EntityID;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, btree_map, HashMap};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::iter::Map;
+use std::sync::{Arc, RwLock};
 
 /// A Component of an Entity
 /// 
@@ -8,7 +14,107 @@ use crate::static_storage::StaticStorage;
 /// to search the datastructure for the corrisbonding Component. This has it's trade offs. On the one hand accessing the Component of an Entity
 /// is much slower than accessing the analogous field of a struct. However this allows for the ability to create Entities with any combination
 /// of Components at runtime, it also allows for the ability to join entities that all have Components in common. 
-pub trait Component<S> : StaticStorage<S> where S : ComponentStorage<Self> + 'static {}
+pub trait Component<S> : StaticStorage<S> + 'static where S : ComponentStorage<Self> + 'static {
+    /// Returns the hooks that are called when this Component is added, replaced or removed.
+    /// This function is implemented by the impl_component! macro.
+    fn hooks() -> &'static ComponentHooks<Self>;
+}
+
+/// A Hook is a function that is called with the EntityID and the value of a Component
+pub type Hook<C> = Arc<dyn Fn(EntityID, &C) + Send + Sync>;
+
+/// A ReplaceHook is a function that is called with the EntityID, the old value and the new value of a Component
+pub type ReplaceHook<C> = Arc<dyn Fn(EntityID, &C, &C) + Send + Sync>;
+
+/// ComponentHooks are the functions that are called when a Component is added to, replaced on or removed from an Entity.
+/// The hooks are called by Entity::add, Entity::rm, the Commands, and when an entity is despawned, calling
+/// component_insert or component_remove on the storage directly doesn't call them. This allows state that is derived
+/// from the components, like a spatial index, to be kept up to date. reset! doesn't call the on_remove hooks either,
+/// so that state has to be reset along with the storage.
+///
+/// The hooks are called while the storage of the Component is acquired for writing, so a hook must not acquire it.
+/// A hook can add more hooks, they are called starting with the next add, replace or remove.
+///
+/// For example Position::hooks().on_add(|id, position| index.insert(id, position))
+pub struct ComponentHooks<C> {
+    on_add: RwLock<Vec<Hook<C>>>,
+    on_replace: RwLock<Vec<ReplaceHook<C>>>,
+    on_remove: RwLock<Vec<Hook<C>>>,
+}
+
+impl<C> ComponentHooks<C> {
+    /// Creates ComponentHooks with no hooks, this is const so that impl_component! can create them in a static
+    pub const fn new() -> Self {
+        ComponentHooks { on_add: RwLock::new(Vec::new()), on_replace: RwLock::new(Vec::new()), on_remove: RwLock::new(Vec::new()) }
+    }
+
+    /// Adds a hook that is called after the Component is added to an entity that didn't have it
+    pub fn on_add<F : Fn(EntityID, &C) + Send + Sync + 'static>(&self, hook : F) {
+        self.on_add.write().expect("Failed to access the ComponentHooks").push(Arc::new(hook));
+    }
+
+    /// Adds a hook that is called before the Component of an entity is replaced, with the old and the new value
+    pub fn on_replace<F : Fn(EntityID, &C, &C) + Send + Sync + 'static>(&self, hook : F) {
+        self.on_replace.write().expect("Failed to access the ComponentHooks").push(Arc::new(hook));
+    }
+
+    /// Adds a hook that is called before the Component is removed from an entity
+    pub fn on_remove<F : Fn(EntityID, &C) + Send + Sync + 'static>(&self, hook : F) {
+        self.on_remove.write().expect("Failed to access the ComponentHooks").push(Arc::new(hook));
+    }
+
+    /// Removes every hook
+    pub fn clear(&self) {
+        self.on_add.write().expect("Failed to access the ComponentHooks").clear();
+        self.on_replace.write().expect("Failed to access the ComponentHooks").clear();
+        self.on_remove.write().expect("Failed to access the ComponentHooks").clear();
+    }
+
+    /// Inserts the Component into the storage, calling the on_replace hooks if the entity already had
+    /// the Component and the on_add hooks otherwise
+    pub fn insert<S : ComponentStorage<C, EntityID=EntityID>>(&self, storage : &mut S, id : EntityID, comp : C) {
+        let replaced = match storage.component_get(id) {
+            Some(old) => {
+                for hook in snapshot(&self.on_replace) {
+                    hook(id, old, &comp);
+                }
+                true
+            },
+            None => false,
+        };
+        storage.component_insert(id, comp);
+        let on_add = snapshot(&self.on_add);
+        if !replaced && !on_add.is_empty() {
+            if let Some(new) = storage.component_get(id) {
+                for hook in on_add {
+                    hook(id, new);
+                }
+            }
+        }
+    }
+
+    /// Removes the Component from the storage, calling the on_remove hooks if the entity had the Component
+    pub fn remove<S : ComponentStorage<C, EntityID=EntityID>>(&self, storage : &mut S, id : EntityID) {
+        if let Some(old) = storage.component_get(id) {
+            for hook in snapshot(&self.on_remove) {
+                hook(id, old);
+            }
+        }
+        storage.component_remove(&id);
+    }
+}
+
+/// snapshot clones a list of hooks so that they aren't called while the lock is held,
+/// which would deadlock a hook that adds another hook
+fn snapshot<H : Clone>(hooks : &RwLock<Vec<H>>) -> Vec<H> {
+    hooks.read().expect("Failed to access the ComponentHooks").clone()
+}
+
+impl<C> Default for ComponentHooks<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// The ComponentStorage trait represents a data structure that stores Components 
 /// Every ComponentStorage is different but Idealy ComponentStorages should be able
@@ -16,19 +122,120 @@ pub trait Component<S> : StaticStorage<S> where S : ComponentStorage<Self> + 'st
 /// each component sorted by EntityID.
 pub trait ComponentStorage<V> : Default {
     type EntityID;
+    /// An iterator over (EntityID, &Component) pairs sorted by EntityID
+    type Iter<'a> : Iterator<Item=(Self::EntityID, &'a V)> where Self : 'a, V : 'a;
+    /// An iterator over (EntityID, &mut Component) pairs sorted by EntityID
+    type IterMut<'a> : Iterator<Item=(Self::EntityID, &'a mut V)> where Self : 'a, V : 'a;
     fn component_insert(&mut self, key : Self::EntityID, value : V);
     fn component_get(&self, key : Self::EntityID) -> Option<&V>;
     fn component_get_mut(&mut self, key : Self::EntityID) -> Option<&mut V>;
     fn component_remove(&mut self, key : &Self::EntityID);
+    /// Iterates through every component in the storage sorted by EntityID, this is used by joins
+    fn component_iter(&self) -> Self::Iter<'_>;
+    /// Mutably iterates through every component in the storage sorted by EntityID, this is used by joins
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_>;
 }
 
 /// A basic implementation for ComponentStorage on the standard BTreeMap
-impl<K: Ord + Copy, V> ComponentStorage<V> for std::collections::BTreeMap<K, V> {
+impl<K: Ord + Copy, V> ComponentStorage<V> for BTreeMap<K, V> {
+    type EntityID = K;
+    type Iter<'a> = Map<btree_map::Iter<'a, K, V>, fn((&'a K, &'a V)) -> (K, &'a V)> where K : 'a, V : 'a;
+    type IterMut<'a> = Map<btree_map::IterMut<'a, K, V>, fn((&'a K, &'a mut V)) -> (K, &'a mut V)> where K : 'a, V : 'a;
+    fn component_insert(&mut self, key : Self::EntityID, value : V){ self.insert(key, value); }
+    fn component_get(&self, key : Self::EntityID) -> Option<&V> { self.get(&key) }
+    fn component_get_mut(&mut self, key : Self::EntityID) -> Option<&mut V> {self.get_mut(&key) }
+    fn component_remove(&mut self, key : &Self::EntityID) { self.remove(key); }
+    fn component_iter(&self) -> Self::Iter<'_> { self.iter().map(|(k, v)| (*k, v)) }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> { self.iter_mut().map(|(k, v)| (*k, v)) }
+}
+
+/// An implementation for ComponentStorage on the standard HashMap, along with FastHashMap. Lookups don't depend on the
+/// number of components, and memory only grows with the number of components, which suits rare components in a large
+/// number of entities. A HashMap isn't sorted, so iterating collects and sorts the components, a join can instead
+/// look up the components of a HashMap with join::Lookup, for example (&**positions, Lookup(&**selected)).join()
+impl<K: Ord + Copy + Hash, V, H: BuildHasher + Default> ComponentStorage<V> for HashMap<K, V, H> {
     type EntityID = K;
+    type Iter<'a> = std::vec::IntoIter<(K, &'a V)> where K : 'a, V : 'a, H : 'a;
+    type IterMut<'a> = std::vec::IntoIter<(K, &'a mut V)> where K : 'a, V : 'a, H : 'a;
     fn component_insert(&mut self, key : Self::EntityID, value : V){ self.insert(key, value); }
     fn component_get(&self, key : Self::EntityID) -> Option<&V> { self.get(&key) }
     fn component_get_mut(&mut self, key : Self::EntityID) -> Option<&mut V> {self.get_mut(&key) }
     fn component_remove(&mut self, key : &Self::EntityID) { self.remove(key); }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        let mut components : Vec<(K, &V)> = self.iter().map(|(k, v)| (*k, v)).collect();
+        components.sort_unstable_by_key(|(k, _)| *k);
+        components.into_iter()
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        let mut components : Vec<(K, &mut V)> = self.iter_mut().map(|(k, v)| (*k, v)).collect();
+        components.sort_unstable_by_key(|(k, _)| *k);
+        components.into_iter()
+    }
+}
+
+/// FastHashMap is a HashMap that uses the FastHasher instead of the default hasher
+pub type FastHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FastHasher>>;
+
+/// FastHasher is a simple multiply and rotate hasher, the same as the one used by the rust compiler (FxHash).
+/// It is much faster than the default hasher for small keys like EntityIDs, but isn't resistant to hash flooding,
+/// which doesn't matter for keys that the ECS creates itself.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FastHasher(u64);
+
+impl FastHasher {
+    const SEED : u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, word : u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for FastHasher {
+    fn write(&mut self, bytes : &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+    fn write_u8(&mut self, i : u8) { self.add(i as u64) }
+    fn write_u16(&mut self, i : u16) { self.add(i as u64) }
+    fn write_u32(&mut self, i : u32) { self.add(i as u64) }
+    fn write_u64(&mut self, i : u64) { self.add(i) }
+    fn write_usize(&mut self, i : usize) { self.add(i as u64) }
+    fn finish(&self) -> u64 { self.0 }
+}
+
+/// remove_entity removes an entity from a ComponentStorage, calling the on_remove hooks of the Component.
+/// It is used by impl_component! to implement StaticStorage::despawn. Storages that aren't keyed by EntityID are left untouched.
+pub fn remove_entity<C, S>(storage : &mut S, id : EntityID) where C : Component<S>, S : ComponentStorage<C> + 'static, S::EntityID : 'static {
+    if let Some(key) = entity_key::<S::EntityID>(id) {
+        // the key isn't required to be Copy, so another one is taken for component_get
+        if let Some(old) = entity_key(id).and_then(|get_key| storage.component_get(get_key)) {
+            for hook in snapshot(&C::hooks().on_remove) {
+                hook(id, old);
+            }
+        }
+        storage.component_remove(&key);
+    }
+}
+
+/// entity_key returns the id as the key type K if K is EntityID, taking the id out of an Option
+/// gives an owned key without requiring the key to be Copy
+fn entity_key<K : 'static>(id : EntityID) -> Option<K> {
+    (&mut Some(id) as &mut dyn Any).downcast_mut::<Option<K>>().and_then(Option::take)
+}
+
+/// despawn_component removes an entity from the storage of the Component C, it acquires write access
+/// to the storage while doing so and will block until it can. impl_component! registers this function
+/// with the EntityRegister so that despawning an entity removes all of it's components.
+/// Storages that aren't keyed by EntityID are ignored.
+pub fn despawn_component<C, S>(id : EntityID) where C : Component<S>, S : ComponentStorage<C> + 'static, S::EntityID : 'static {
+    if TypeId::of::<S::EntityID>() == TypeId::of::<EntityID>() {
+        let mut guard = acquire!(Write(C));
+        let (storage,) = guard.get();
+        C::despawn(storage, id);
+    }
 }
 
 /// impl_component! macro implements the Component Trait on a given struct
@@ -40,26 +247,47 @@ impl<K: Ord + Copy, V> ComponentStorage<V> for std::collections::BTreeMap<K, V>
 #[macro_export] macro_rules! impl_component {
     ($name:ty, $storage:ty) => {
         impl genecs::static_storage::StaticStorage<$storage> for $name {
-            unsafe fn get_static() -> &'static mut ($storage, (u64, u64)) {
-                // Import std::sync::Once and std::sync::ONCE_INIT as the user might not import themselves
-                use std::sync::{Once, ONCE_INIT};
+            fn init() -> $storage {
                 // Use an Inner type to call ::defualt as types with generic arguments will cause an error
                 type ComponentStorageInner = $storage;
+                ComponentStorageInner::default()
+            }
+            fn despawn(storage : &mut $storage, id : genecs::entity::EntityID) {
+                genecs::component::remove_entity::<$name, _>(storage, id);
+            }
+            unsafe fn get_static() -> &'static mut $storage {
+                // Import std::sync::Once as the user might not import it themselves
+                use std::sync::Once;
                 // Initialize the static storage to a const (Option, Once)
-                static mut STATIC_STORAGE : (Option<($storage, (u64, u64))>, Once) = (None, ONCE_INIT);
-                // At runtime Change the static storages None to a Some($storage, (u64, u64))
-                STATIC_STORAGE.1.call_once(|| {
-                    STATIC_STORAGE.0 = Some((ComponentStorageInner::default(), (0,0)));
+                static mut STATIC_STORAGE : (Option<$storage>, Once) = (None, Once::new());
+                // At runtime Change the static storages None to a Some($storage)
+                (*std::ptr::addr_of!(STATIC_STORAGE.1)).call_once(|| {
+                    STATIC_STORAGE.0 = Some(<Self as genecs::static_storage::StaticStorage<$storage>>::init());
+                    // Register the storage so that despawned entities are removed from it
+                    genecs::entity::EntityRegister::register_storage(genecs::component::despawn_component::<$name, $storage>);
                 });
                 // Coerce a mutable refrence into a mutable pointer
-                let ptr = &mut STATIC_STORAGE.0 as * mut Option<($storage, (u64, u64))>;
+                let ptr = std::ptr::addr_of_mut!(STATIC_STORAGE.0);
                 // Unwrap the option and return the inner storage
                 match *ptr {
                     Some(ref mut x) => x,
                     None => unreachable!(),
                 }
             }
+            fn get_lock() -> &'static genecs::static_storage::StorageLock {
+                // The lock is const created, so unlike the storage it doesn't need to be initialized at runtime
+                static LOCK : genecs::static_storage::StorageLock = genecs::static_storage::StorageLock::new();
+                &LOCK
+            }
+        }
+        impl genecs::static_storage::Stored for $name {
+            type Storage = $storage;
+        }
+        impl Component<$storage> for $name {
+            fn hooks() -> &'static genecs::component::ComponentHooks<$name> {
+                static HOOKS : genecs::component::ComponentHooks<$name> = genecs::component::ComponentHooks::new();
+                &HOOKS
+            }
         }
-        impl Component<$storage> for $name {}
     }
 }
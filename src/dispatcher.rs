@@ -0,0 +1,307 @@
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use crate::static_storage::Access;
+use crate::thread_pool::ThreadPool;
+use crate::commands::Commands;
+use crate::system::{System, SystemData, RunCriteria, should_run};
+
+/// The Dispatcher runs a list of systems, running systems that don't use the same storages in parallel.
+/// The Dispatcher is created with a DispatcherBuilder, which uses the SystemData of each system to split the
+/// systems into stages. Each stage is a group of systems that don't conflict, two systems conflict if they use the
+/// same storage and atleast one of them writes to it. The stages are run one after another, and the systems in a stage
+/// are run in parallel.
+///
+/// If two systems conflict then the one that was added to the builder first is run first, so the systems will
+/// always observe the same order of writes as if they were run with dispatch!. Systems can also be given labels
+/// and ordered with before and after, these constraints take priority over the order the systems were added in.
+///
+/// The Dispatcher has it's own Commands, the commands the systems record into the Commands resource go into it and
+/// are applied at the end of every stage, so the systems of a stage see the changes made by the commands of the stages
+/// before it. The commands of other Dispatchers and threads are left alone.
+///
+/// The systems are run on the global ThreadPool unless a different ThreadPool is given to the builder.
+pub struct Dispatcher {
+    stages: Vec<Vec<Entry>>,
+    pool: Option<Arc<ThreadPool>>,
+    commands: Commands,
+}
+
+/// DispatcherBuilder collects the systems of a Dispatcher, the stages are computed by build
+pub struct DispatcherBuilder {
+    entries: Vec<Entry>,
+    pool: Option<Arc<ThreadPool>>,
+}
+
+/// An Entry is a system of the Dispatcher along with it's name, it's ordering constraints and the storages it uses
+struct Entry {
+    name: String,
+    labels: Vec<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+    criteria: Vec<RunCriteria>,
+    accesses: Vec<(TypeId, Access)>,
+    system: Box<dyn RunSystem>,
+}
+
+/// An Enum that represents the errors that can occur while building a Dispatcher
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The ordering constraints form a cycle, contains the names of the systems in the cycle
+    /// with the first system repeated at the end
+    Cycle(Vec<String>),
+    /// A system is ordered before or after a label that no system has
+    UnknownLabel { system: String, label: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Cycle(names) => write!(f, "The ordering of the systems contains a cycle: {}", names.join(" -> ")),
+            Error::UnknownLabel { system, label } => write!(f, "The system {} is ordered with the label {} but no system has that label", system, label),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// RunSystem is an object safe version of System, this allows the Dispatcher to store systems of different types
+trait RunSystem : Send {
+    fn dispatch(&mut self);
+}
+
+impl<S : System + Send> RunSystem for S {
+    fn dispatch(&mut self) {
+        System::dispatch(self)
+    }
+}
+
+/// Returns true if the two lists of accesses use the same storage and atleast one of them writes to it
+pub fn conflicts(first : &[(TypeId, Access)], second : &[(TypeId, Access)]) -> bool {
+    first.iter().any(|(a, a_access)| {
+        second.iter().any(|(b, b_access)| a == b && (*a_access == Access::Write || *b_access == Access::Write))
+    })
+}
+
+impl DispatcherBuilder {
+    /// Creates a DispatcherBuilder with no systems
+    pub fn new() -> Self {
+        DispatcherBuilder { entries: Vec::new(), pool: None }
+    }
+
+    /// Runs the systems on the given ThreadPool instead of the global ThreadPool
+    pub fn with_thread_pool(mut self, pool : Arc<ThreadPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Adds a system to the Dispatcher, the name is used to identify the system in the plan
+    /// and can be used as a label by before and after
+    pub fn with<S : System + Send + 'static>(mut self, system : S, name : &str) -> Self {
+        self.entries.push(Entry {
+            name: name.to_string(),
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            criteria: Vec::new(),
+            accesses: S::SystemData::accesses(),
+            system: Box::new(system),
+        });
+        self
+    }
+
+    /// Gives the last system that was added a label, several systems can share the same label
+    pub fn label(mut self, label : &str) -> Self {
+        self.last("label").labels.push(label.to_string());
+        self
+    }
+
+    /// Orders the last system that was added before every system with the label
+    pub fn before(mut self, label : &str) -> Self {
+        self.last("before").before.push(label.to_string());
+        self
+    }
+
+    /// Orders the last system that was added after every system with the label
+    pub fn after(mut self, label : &str) -> Self {
+        self.last("after").after.push(label.to_string());
+        self
+    }
+
+    /// Adds a RunCriteria to the last system that was added, the system is only run when all of it's criteria pass.
+    /// The criteria of a stage are checked one after another before any of it's systems are run.
+    pub fn run_if<C : FnMut() -> bool + Send + 'static>(mut self, criteria : C) -> Self {
+        self.last("run_if").criteria.push(Box::new(criteria));
+        self
+    }
+
+    /// Returns the last system that was added
+    fn last(&mut self, function : &str) -> &mut Entry {
+        match self.entries.last_mut() {
+            Some(entry) => entry,
+            None => panic!("{} must be called after a system is added with with", function),
+        }
+    }
+
+    /// Computes the stages and creates the Dispatcher, see try_build.
+    /// This function panics if the ordering constraints can't be satisfied.
+    pub fn build(self) -> Dispatcher {
+        match self.try_build() {
+            Ok(dispatcher) => dispatcher,
+            Err(error) => panic!("Failed to build the Dispatcher: {}", error),
+        }
+    }
+
+    /// Computes the stages and creates the Dispatcher. The systems are first ordered by their before and after
+    /// constraints, using the order they were added in when there is no constraint between them. Each system is
+    /// then put in the stage after the last stage that contains a system it is ordered after or conflicts with.
+    /// An Error::Cycle is returned if the constraints form a cycle, and an Error::UnknownLabel if a constraint
+    /// uses a label that no system has.
+    pub fn try_build(self) -> Result<Dispatcher, Error> {
+        let entries = self.entries;
+        // every system is labeled with it's name
+        let mut labels : HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            for label in std::iter::once(&entry.name).chain(entry.labels.iter()) {
+                labels.entry(label.as_str()).or_default().push(i);
+            }
+        }
+        // predecessors[i] contains the systems that must run before system i
+        let mut predecessors : Vec<BTreeSet<usize>> = vec![BTreeSet::new(); entries.len()];
+        for (i, entry) in entries.iter().enumerate() {
+            for (label, is_before) in entry.before.iter().map(|l| (l, true)).chain(entry.after.iter().map(|l| (l, false))) {
+                let others = labels.get(label.as_str()).ok_or_else(|| Error::UnknownLabel { system: entry.name.clone(), label: label.clone() })?;
+                for &other in others.iter().filter(|&&other| other != i) {
+                    if is_before {
+                        predecessors[other].insert(i);
+                    } else {
+                        predecessors[i].insert(other);
+                    }
+                }
+            }
+        }
+        let order = order(&predecessors).map_err(|cycle| Error::Cycle(cycle.into_iter().map(|i| entries[i].name.clone()).collect()))?;
+
+        // a system runs in the stage after the systems it is ordered after, and the systems earlier in the order it conflicts with
+        let mut stage_of = vec![0; entries.len()];
+        for (position, &i) in order.iter().enumerate() {
+            let conflicting = order[..position].iter().filter(|&&other| conflicts(&entries[i].accesses, &entries[other].accesses));
+            stage_of[i] = predecessors[i].iter().chain(conflicting)
+                .map(|&other| stage_of[other] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        let mut entries : Vec<Option<Entry>> = entries.into_iter().map(Some).collect();
+        let mut stages : Vec<Vec<Entry>> = Vec::new();
+        for i in order {
+            if stage_of[i] >= stages.len() {
+                stages.resize_with(stage_of[i] + 1, Vec::new);
+            }
+            stages[stage_of[i]].push(entries[i].take().expect("A system was ordered twice"));
+        }
+        Ok(Dispatcher { stages, pool: self.pool, commands: Commands::new() })
+    }
+}
+
+/// order sorts the systems so that every system comes after it's predecessors, using the order they were
+/// added in when there is no constraint between them. If the predecessors form a cycle it is returned instead,
+/// starting and ending with the same system.
+fn order(predecessors : &[BTreeSet<usize>]) -> Result<Vec<usize>, Vec<usize>> {
+    let mut remaining : Vec<usize> = predecessors.iter().map(|p| p.len()).collect();
+    let mut successors : Vec<Vec<usize>> = vec![Vec::new(); predecessors.len()];
+    for (i, p) in predecessors.iter().enumerate() {
+        for &other in p {
+            successors[other].push(i);
+        }
+    }
+    let mut ready : BTreeSet<usize> = (0..predecessors.len()).filter(|&i| remaining[i] == 0).collect();
+    let mut order = Vec::with_capacity(predecessors.len());
+    while let Some(i) = ready.pop_first() {
+        order.push(i);
+        for &next in successors[i].iter() {
+            remaining[next] -= 1;
+            if remaining[next] == 0 {
+                ready.insert(next);
+            }
+        }
+    }
+    if order.len() == predecessors.len() {
+        return Ok(order);
+    }
+    // Every system that wasn't ordered has a predecessor that wasn't ordered, so following
+    // the predecessors from one of them must eventually repeat a system
+    let mut path = vec![(0..predecessors.len()).find(|&i| remaining[i] > 0).expect("A cycle has atleast one system")];
+    loop {
+        let last = path[path.len() - 1];
+        let previous = *predecessors[last].iter().find(|&&p| remaining[p] > 0).expect("An unordered system has an unordered predecessor");
+        if let Some(start) = path.iter().position(|&i| i == previous) {
+            // The path follows predecessors, so reverse it to get the order the systems would run in,
+            // and start the cycle from the system that was added first
+            let mut cycle : Vec<usize> = path[start..].iter().rev().cloned().collect();
+            let first = cycle.iter().enumerate().min_by_key(|(_, &i)| i).map_or(0, |(position, _)| position);
+            cycle.rotate_left(first);
+            cycle.push(cycle[0]);
+            return Err(cycle);
+        }
+        path.push(previous);
+    }
+}
+
+impl Default for DispatcherBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dispatcher {
+    /// Creates a DispatcherBuilder
+    pub fn builder() -> DispatcherBuilder {
+        DispatcherBuilder::new()
+    }
+
+    /// Runs every stage in order, the systems of a stage are run in parallel.
+    /// Systems whose RunCriteria fail are skipped, and the Commands recorded by the systems are applied after each stage.
+    pub fn dispatch(&mut self) {
+        let Dispatcher { stages, pool, commands } = self;
+        let pool : &ThreadPool = match pool {
+            Some(pool) => pool,
+            None => ThreadPool::global(),
+        };
+        let commands : &Commands = commands;
+        for stage in stages.iter_mut() {
+            let mut stage : Vec<&mut Entry> = stage.iter_mut()
+                .filter_map(|entry| if should_run(&mut entry.criteria) { Some(entry) } else { None })
+                .collect();
+            match stage.as_mut_slice() {
+                [] => {},
+                // There is no need to use the pool for a single system
+                [entry] => commands.record(|| entry.system.dispatch()),
+                stage => pool.scope(|s| {
+                    for entry in stage.iter_mut() {
+                        s.spawn(move || commands.record(|| entry.system.dispatch()));
+                    }
+                }),
+            }
+            // Commands recorded while applying are left for the next stage
+            commands.record(|| commands.apply());
+        }
+    }
+
+    /// Returns the computed plan, the names of the systems in each stage
+    pub fn stages(&self) -> Vec<Vec<&str>> {
+        self.stages.iter()
+            .map(|stage| stage.iter().map(|entry| entry.name.as_str()).collect())
+            .collect()
+    }
+}
+
+/// Displays the plan of the Dispatcher with one stage per line
+impl fmt::Display for Dispatcher {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        for (i, stage) in self.stages().iter().enumerate() {
+            writeln!(f, "stage {}: {}", i, stage.join(", "))?;
+        }
+        Ok(())
+    }
+}
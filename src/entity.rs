@@ -1,10 +1,38 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
 use crate::component::{Component, ComponentStorage};
 /// The EntityRegister is a empty struct that can be used to generate
-/// new unique entity ids. The entity ids are of type usize and start
-/// at 0. The Entity Register is effectively an atomic counter.
+/// new unique entity ids. When an id is freed it's index is recycled
+/// for a later entity with the generation incremented, so that the freed
+/// id can be told apart from the new one.
 pub struct EntityRegister();
-pub type EntityID = usize;
+
+/// EntityID is the unique identifier of an Entity. It is made up of an index
+/// and a generation, the index is reused once the entity is freed, but the
+/// generation is incremented each time that happens. EntityIDs are sorted by
+/// index then generation.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct EntityID {
+    index: u32,
+    generation: u32,
+}
+
+impl EntityID {
+    /// Creates an EntityID from an index and generation, this does not register
+    /// the id with the EntityRegister, use EntityRegister::get_new_id for that
+    pub fn new(index : u32, generation : u32) -> Self {
+        EntityID { index, generation }
+    }
+
+    /// Get the index of this id, the index can be shared with freed entities
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Get the generation of this id
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
 
 /// Entity is a simple wrapper around EntityID and interfaces with
 /// the component system to allow for reduced boiler plate
@@ -17,57 +45,258 @@ impl<'a> Entity{
         Entity(EntityRegister::get_new_id())
     }
 
-    /// Adds a component to this entity 
-    pub fn add<C,S>(self, storage : &'a mut S, comp : C) -> Self where C : Component<S>, S : ComponentStorage<C, EntityID=usize> + 'static {
-        storage.component_insert(self.0, comp);
+    /// Adds a component to this entity, calling the on_add or on_replace hooks of the component
+    pub fn add<C,S>(self, storage : &'a mut S, comp : C) -> Self where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + 'static {
+        C::hooks().insert(storage, self.0, comp);
         self
     }
 
-    /// Removes a component from this entity
-    pub fn rm<C,S>(self, storage : &'a mut S) where C : Component<S>, S : ComponentStorage<C, EntityID=usize> + 'static {
-        storage.component_remove(&self.0);
+    /// Removes a component from this entity, calling the on_remove hooks of the component
+    pub fn rm<C,S>(self, storage : &'a mut S) where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + 'static {
+        C::hooks().remove(storage, self.0);
+    }
+
+    /// Gets a refrence to a component of this entity, None is returned if
+    /// the entity doesn't have the component or the entity is no longer alive.
+    /// This checks the EntityRegister, use get_in for entities of a World
+    pub fn get<C,S>(&self, storage : &'a S) -> Option<&'a C> where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + 'static {
+        self.get_in(&ENTITY_REGISTER, storage)
+    }
+
+    /// Get a mutable refrence to a component of this entity, None is returned if
+    /// the entity doesn't have the component or the entity is no longer alive.
+    /// This checks the EntityRegister, use get_mut_in for entities of a World
+    pub fn get_mut<C,S>(&mut self, storage : &'a mut S) -> Option<&'a mut C> where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + 'static {
+        self.get_mut_in(&ENTITY_REGISTER, storage)
+    }
+
+    /// The same as get, but checks if the entity is alive in the EntityAllocator that created it, such as World::entities
+    pub fn get_in<C,S>(&self, entities : &EntityAllocator, storage : &'a S) -> Option<&'a C> where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + 'static {
+        if entities.is_alive(self.0) { storage.component_get(self.0) } else { None }
+    }
+
+    /// The same as get_mut, but checks if the entity is alive in the EntityAllocator that created it, such as World::entities
+    pub fn get_mut_in<C,S>(&mut self, entities : &EntityAllocator, storage : &'a mut S) -> Option<&'a mut C> where C : Component<S>, S : ComponentStorage<C, EntityID=EntityID> + 'static {
+        if entities.is_alive(self.0) { storage.component_get_mut(self.0) } else { None }
     }
 
-    /// Gets a refrence to a component of this entity
-    pub fn get<C,S>(&self, storage : &'a S) -> Option<&'a C> where C : Component<S>, S : ComponentStorage<C, EntityID=usize> + 'static {
-        storage.component_get(self.0)
+    /// Removes every component of this entity and frees it's id, see EntityRegister::despawn
+    pub fn despawn(self) -> bool {
+        EntityRegister::despawn(self.0)
     }
 
-    /// Get a mutable refrence to a component of this entity
-    pub fn get_mut<C,S>(&mut self, storage : &'a mut S) -> Option<&'a mut C> where C : Component<S>, S : ComponentStorage<C, EntityID=usize> + 'static {
-        storage.component_get_mut(self.0)
+    /// Queues this entity to be despawned, see EntityRegister::despawn_later
+    pub fn despawn_later(self) {
+        EntityRegister::despawn_later(self.0)
+    }
+
+    /// Returns true if the id of this entity hasn't been freed by the EntityRegister, use is_alive_in for entities of a World
+    pub fn is_alive(&self) -> bool {
+        EntityRegister::is_alive(self.0)
+    }
+
+    /// Returns true if the id of this entity hasn't been freed by the EntityAllocator, such as World::entities
+    pub fn is_alive_in(&self, entities : &EntityAllocator) -> bool {
+        entities.is_alive(self.0)
     }
 
     /// Get the id of this entity
-    pub fn get_id(&self) -> usize {
+    pub fn get_id(&self) -> EntityID {
         self.0
     }
 }
 
-impl From<usize> for Entity{
-    fn from(id : usize) -> Entity {
+impl Default for Entity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<EntityID> for Entity{
+    fn from(id : EntityID) -> Entity {
         Entity(id)
     }
 }
 
-impl From<&usize> for Entity{
-    fn from(id : &usize) -> Entity {
+impl From<&EntityID> for Entity{
+    fn from(id : &EntityID) -> Entity {
         Entity(*id)
     }
 }
 
-/// Initialize EntityIDs to 0
-static ENTITY_REGISTER : AtomicUsize = AtomicUsize::new(0);
+/// The Allocator keeps track of the current generation of each index, whether the
+/// index is in use, and the indices that have been freed and can be reused
+struct Allocator {
+    generations: Vec<(u32, bool)>,
+    free: Vec<u32>,
+}
+
+/// The generation of an index that has been freed with every other generation, the index is never reused
+const RETIRED : u32 = u32::MAX;
+
+/// Increments the generation of a freed index so that the freed id can't be confused with the next id at
+/// the index. Returns false if the index ran out of generations and was retired.
+fn next_generation(generation : &mut u32) -> bool {
+    *generation = generation.saturating_add(1);
+    *generation != RETIRED
+}
+
+/// EntityAllocator hands out unique EntityIDs and recycles the indices of freed ids.
+/// The EntityRegister uses a static EntityAllocator, while each World owns it's own.
+pub struct EntityAllocator(RwLock<Allocator>);
+
+/// Initialize the register with no entities
+static ENTITY_REGISTER : EntityAllocator = EntityAllocator::new();
+
+/// A function for each ComponentStorage that removes an entity from it, these are registered by impl_component!
+static DESPAWNERS : Mutex<Vec<fn(EntityID)>> = Mutex::new(Vec::new());
+
+/// The entities waiting to be despawned by EntityRegister::despawn_queued
+static DESPAWN_QUEUE : Mutex<Vec<EntityID>> = Mutex::new(Vec::new());
+
+impl EntityAllocator {
+    /// Creates an EntityAllocator with no entities
+    pub const fn new() -> Self {
+        EntityAllocator(RwLock::new(Allocator { generations: Vec::new(), free: Vec::new() }))
+    }
+
+    /// Returns a new unique id, reusing the index of a freed id if there is one
+    pub fn allocate(&self) -> EntityID {
+        Self::allocate_inner(&mut self.0.write().expect("Failed to access the EntityAllocator"))
+    }
+
+    /// Returns several new unique ids
+    pub fn allocate_many(&self, num : usize) -> Vec<EntityID> {
+        let mut allocator = self.0.write().expect("Failed to access the EntityAllocator");
+        (0..num).map(|_| Self::allocate_inner(&mut allocator)).collect()
+    }
+
+    /// Frees an id so that it's index can be reused by a new entity. The id is no longer
+    /// alive after this call. Returns false if the id was already freed. An index that has
+    /// run out of generations is retired instead of being reused, so an old id never comes back to life.
+    pub fn free(&self, id : EntityID) -> bool {
+        let mut allocator = self.0.write().expect("Failed to access the EntityAllocator");
+        match allocator.generations.get_mut(id.index()) {
+            Some((generation, alive)) if *generation == id.generation && *alive => {
+                *alive = false;
+                if next_generation(generation) {
+                    allocator.free.push(id.index);
+                }
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns true if the id was created by the allocator and hasn't been freed
+    pub fn is_alive(&self, id : EntityID) -> bool {
+        let allocator = self.0.read().expect("Failed to access the EntityAllocator");
+        allocator.generations.get(id.index()) == Some(&(id.generation, true))
+    }
+
+    /// Frees every id, the next id allocated will have an index of 0. The generation of every id that was
+    /// alive is incremented, so the ids from before the clear stay dead after their indices are reused.
+    /// Indices that have run out of generations stay retired.
+    pub fn clear(&self) {
+        let mut allocator = self.0.write().expect("Failed to access the EntityAllocator");
+        for (generation, alive) in allocator.generations.iter_mut() {
+            if *alive {
+                *alive = false;
+                next_generation(generation);
+            }
+        }
+        // the free list is popped from the back, so the lowest index is reused first
+        let free = (0..allocator.generations.len() as u32).rev()
+            .filter(|index| allocator.generations[*index as usize].0 != RETIRED)
+            .collect();
+        allocator.free = free;
+    }
+
+    /// Returns a freed index if there is one, otherwise creates a new index
+    fn allocate_inner(allocator : &mut Allocator) -> EntityID {
+        match allocator.free.pop() {
+            Some(index) => {
+                let entry = &mut allocator.generations[index as usize];
+                entry.1 = true;
+                EntityID::new(index, entry.0)
+            },
+            None => {
+                allocator.generations.push((0, true));
+                EntityID::new((allocator.generations.len() - 1) as u32, 0)
+            }
+        }
+    }
+}
+
+impl Default for EntityAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl EntityRegister {
     /// Returns a new unique id that can be used for this entity
-    pub fn get_new_id() -> usize {
-        ENTITY_REGISTER.fetch_add(1, Ordering::Relaxed)
+    pub fn get_new_id() -> EntityID {
+        ENTITY_REGISTER.allocate()
+    }
+
+    /// Returns several new unique ids that can be used for as entities
+    pub fn get_new_ids(num: usize) -> Vec<EntityID> {
+        ENTITY_REGISTER.allocate_many(num)
+    }
+
+    /// Frees an id so that it's index can be reused by a new entity. The id is no longer
+    /// alive after this call. Returns false if the id was already freed.
+    pub fn free_id(id : EntityID) -> bool {
+        ENTITY_REGISTER.free(id)
+    }
+
+    /// Returns true if the id was created by the register and hasn't been freed
+    pub fn is_alive(id : EntityID) -> bool {
+        ENTITY_REGISTER.is_alive(id)
+    }
+
+    /// Frees every id and clears the despawn queue, the next id created will have an index of 0 and the ids from before stay dead.
+    /// This does not remove any components, the component storages can be cleared with the reset! macro.
+    pub fn reset() {
+        DESPAWN_QUEUE.lock().expect("Failed to access the despawn queue").clear();
+        ENTITY_REGISTER.clear();
+    }
+
+    /// Registers a function that removes an entity from a ComponentStorage. This is called by impl_component!
+    /// the first time a storage is used, so that despawn can remove entities from every storage.
+    pub fn register_storage(despawner : fn(EntityID)) {
+        DESPAWNERS.lock().expect("Failed to access the despawners").push(despawner);
+    }
+
+    /// Removes the entity from every registered ComponentStorage and then frees it's id.
+    /// Each storage is acquired for writing one at a time, so this function blocks and will deadlock
+    /// if called while holding a guard to a component storage, such as inside of a system.
+    /// Use despawn_later in that case. Returns false if the id was already freed.
+    pub fn despawn(id : EntityID) -> bool {
+        if !Self::is_alive(id) {
+            return false;
+        }
+        // Copy the despawners so that storages can be registered while they run
+        let despawners = DESPAWNERS.lock().expect("Failed to access the despawners").clone();
+        for despawner in despawners {
+            despawner(id);
+        }
+        Self::free_id(id)
+    }
+
+    /// Queues the entity to be despawned the next time despawn_queued is called. This doesn't
+    /// acquire any storages, so it is safe to call inside of systems.
+    pub fn despawn_later(id : EntityID) {
+        DESPAWN_QUEUE.lock().expect("Failed to access the despawn queue").push(id);
     }
 
-    /// Returns a range of new unique ids that can be used for as entities
-    pub fn get_new_ids(num: usize) -> std::ops::Range<usize> {
-        let start = ENTITY_REGISTER.fetch_add(num, Ordering::Relaxed);
-        (start..start+num)
+    /// Despawns every entity queued by despawn_later. This should be called between dispatches
+    /// when no guards are held, for example at the end of every frame.
+    pub fn despawn_queued() {
+        let queue = std::mem::take(&mut *DESPAWN_QUEUE.lock().expect("Failed to access the despawn queue"));
+        for id in queue {
+            Self::despawn(id);
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,218 @@
+use std::collections::{VecDeque, vec_deque};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::static_storage::{StaticStorage, Stored};
+use crate::system::{System, Write, Data};
+
+/// An Event is a message that is sent from one system to any number of other systems. The Events of a type
+/// are stored in a static Events<T>, which is acquired like any other storage with acquire!(Write(Collision))
+/// to send events, or acquire!(Read(Collision)) to read them.
+pub trait Event : StaticStorage<Events<Self>> + Send + Sync + 'static {}
+
+/// Events is the storage of an Event, it is a queue of the events that have been sent. Every reader has it's
+/// own cursor, so each reader sees every event once no matter how many readers there are. Reading only needs
+/// a refrence to the Events, so systems can read events in parallel.
+///
+/// Events are dropped by update once every reader has read them, or once they have been in the queue for
+/// two updates, so events aren't kept forever if a reader stops reading. update should be called once a frame,
+/// for example by adding the UpdateEvents system to the start of a Dispatcher.
+pub struct Events<T> {
+    /// The number of this Events, so a reader can tell if it is used with the Events that created it
+    instance: u64,
+    events: VecDeque<T>,
+    /// The number of the first event in the queue, each event is numbered in the order it was sent
+    start: u64,
+    /// The number of the first event sent since the last update
+    frame_start: u64,
+    /// The cursor of each reader, the number of the next event it will read. None if the reader was removed
+    readers: Mutex<Vec<Option<u64>>>,
+}
+
+/// The number of the next Events that is created
+static NEXT_INSTANCE : AtomicU64 = AtomicU64::new(0);
+
+/// An EventReader is a cursor into an Events<T>, it is created by Events::reader and remembers which events it has read.
+/// If the reader is used with a different Events, like one that replaced it's Events with reset!, it starts over
+/// as a new reader of that Events that reads every event in the queue.
+pub struct EventReader<T> {
+    /// The instance of the Events the reader belongs to
+    instance: u64,
+    id: usize,
+    _event: PhantomData<fn() -> T>,
+}
+
+impl<T> Events<T> {
+    /// Creates an empty Events with no readers
+    pub fn new() -> Self {
+        Events { instance: NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed), events: VecDeque::new(), start: 0, frame_start: 0, readers: Mutex::new(Vec::new()) }
+    }
+
+    /// Sends an event to every reader
+    pub fn send(&mut self, event : T) {
+        self.events.push_back(event);
+    }
+
+    /// Returns the number of events in the queue
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if there are no events in the queue
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Creates a new reader, the reader will read every event sent after it was created
+    pub fn reader(&self) -> EventReader<T> {
+        self.reader_from(self.end())
+    }
+
+    /// Removes a reader, so that events are no longer kept until the reader has read them
+    pub fn remove_reader(&self, reader : EventReader<T>) {
+        if reader.instance != self.instance {
+            return;
+        }
+        if let Some(cursor) = self.readers.lock().expect("Failed to access the EventReaders").get_mut(reader.id) {
+            *cursor = None;
+        }
+    }
+
+    /// Creates a new reader whose next event is the event with the number cursor
+    fn reader_from(&self, cursor : u64) -> EventReader<T> {
+        let mut readers = self.readers.lock().expect("Failed to access the EventReaders");
+        readers.push(Some(cursor));
+        EventReader { instance: self.instance, id: readers.len() - 1, _event: PhantomData }
+    }
+
+    /// Drops the events that have been read by every reader, along with the events that were
+    /// sent before the last update. This should be called once a frame.
+    pub fn update(&mut self) {
+        let readers = self.readers.get_mut().expect("Failed to access the EventReaders");
+        let read = readers.iter().flatten().min().copied();
+        // events are kept for two updates, or until every reader has read them
+        let keep_from = match read {
+            Some(read) => read.max(self.frame_start),
+            None => self.frame_start,
+        };
+        let drop = (keep_from.saturating_sub(self.start) as usize).min(self.events.len());
+        self.events.drain(..drop);
+        self.start += drop as u64;
+        self.frame_start = self.end();
+    }
+
+    /// Removes every event, readers will not see the removed events
+    pub fn clear(&mut self) {
+        self.start = self.end();
+        self.frame_start = self.start;
+        self.events.clear();
+    }
+
+    /// Returns the number that the next event sent will have
+    fn end(&self) -> u64 {
+        self.start + self.events.len() as u64
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EventReader<T> {
+    /// Returns the events that this reader hasn't read yet, and marks them as read. Events that were dropped
+    /// before the reader read them are skipped. If the reader belongs to a different Events it becomes
+    /// a reader of events, and every event in the queue is returned.
+    pub fn read<'a>(&mut self, events : &'a Events<T>) -> vec_deque::Iter<'a, T> {
+        if self.instance != events.instance {
+            *self = events.reader_from(events.start);
+        }
+        let mut readers = events.readers.lock().expect("Failed to access the EventReaders");
+        let cursor = match readers.get_mut(self.id).and_then(|cursor| cursor.as_mut()) {
+            Some(cursor) => cursor,
+            None => unreachable!("An EventReader is only removed by remove_reader, which takes the reader"),
+        };
+        let from = (cursor.saturating_sub(events.start) as usize).min(events.events.len());
+        *cursor = events.end();
+        events.events.range(from..)
+    }
+}
+
+impl<T : Event> EventReader<T> {
+    /// Creates a new reader of the static Events of T, this acquires the Events for reading
+    pub fn new() -> Self {
+        let mut guard = acquire!(Read(T));
+        let (events,) = guard.get();
+        events.reader()
+    }
+}
+
+impl<T : Event> Default for EventReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// UpdateEvents is a system that calls update on the Events of T, it should be run once a frame
+pub struct UpdateEvents<T>(PhantomData<fn() -> T>);
+
+impl<T> UpdateEvents<T> {
+    /// Creates the system
+    pub fn new() -> Self {
+        UpdateEvents(PhantomData)
+    }
+}
+
+impl<T> Default for UpdateEvents<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T : Event + Stored<Storage = Events<T>>> System for UpdateEvents<T> {
+    type SystemData = Write<T>;
+    fn run(&mut self, events : Data<Self>) {
+        events.update();
+    }
+}
+
+/// impl_event! macro implements the Event Trait on a given struct, creating a static Events
+/// that stores the events of that type.
+///
+/// For example impl_event!(Collision) allows acquire!(Write(Collision)) to return a &mut Events<Collision>
+#[macro_export] macro_rules! impl_event {
+    ($name:ty) => {
+        impl genecs::static_storage::StaticStorage<genecs::events::Events<$name>> for $name {
+            fn init() -> genecs::events::Events<$name> {
+                genecs::events::Events::new()
+            }
+            unsafe fn get_static() -> &'static mut genecs::events::Events<$name> {
+                // Import std::sync::Once as the user might not import it themselves
+                use std::sync::Once;
+                // Initialize the static storage to a const (Option, Once)
+                static mut STATIC_STORAGE : (Option<genecs::events::Events<$name>>, Once) = (None, Once::new());
+                // At runtime Change the static storages None to a Some(Events)
+                (*std::ptr::addr_of!(STATIC_STORAGE.1)).call_once(|| {
+                    STATIC_STORAGE.0 = Some(<Self as genecs::static_storage::StaticStorage<genecs::events::Events<$name>>>::init());
+                });
+                // Coerce a mutable refrence into a mutable pointer
+                let ptr = std::ptr::addr_of_mut!(STATIC_STORAGE.0);
+                // Unwrap the option and return the inner storage
+                match *ptr {
+                    Some(ref mut x) => x,
+                    None => unreachable!(),
+                }
+            }
+            fn get_lock() -> &'static genecs::static_storage::StorageLock {
+                // The lock is const created, so unlike the storage it doesn't need to be initialized at runtime
+                static LOCK : genecs::static_storage::StorageLock = genecs::static_storage::StorageLock::new();
+                &LOCK
+            }
+        }
+        impl genecs::static_storage::Stored for $name {
+            type Storage = genecs::events::Events<$name>;
+        }
+        impl Event for $name {}
+    }
+}
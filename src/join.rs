@@ -0,0 +1,387 @@
+use crate::component::ComponentStorage;
+use std::iter::Fuse;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The Join trait is implemented on refrences to ComponentStorages and on tuples of Joins.
+/// Calling join on a tuple of storages produces an iterator over every EntityID that has a
+/// component in each of the storages, along with the components themselves.
+///
+/// For example (&a, &mut b).join() will iterate through (EntityID, (&A, &mut B)) sorted by EntityID.
+///
+/// Storages can be wrapped in Optional to yield Option<&C> instead of skipping entities that
+/// don't have the component, or in Not to skip every entity that does have the component.
+/// Storages wrapped in Lookup are probed for each entity instead of being iterated.
+/// For example (&a, Optional(&b), Not(&c)).join() will iterate through (EntityID, (&A, Option<&B>, ()))
+/// for every entity that has an A component and no C component.
+///
+/// Calling single instead of join returns the only (EntityID, Item), or an error if there isn't exactly one.
+///
+/// The generic argument C is the Component (or tuple of Components) being joined, it is
+/// inferred from the ComponentStorage and doesn't need to be specified by the user.
+pub trait Join<C> : Sized {
+    type Source : JoinSource;
+
+    /// Converts the Join into the JoinSource that will drive the JoinIter
+    fn into_source(self) -> Self::Source;
+
+    /// Creates an iterator over all of the EntityIDs shared by the joined storages
+    fn join(self) -> JoinIter<Self::Source> {
+        JoinIter(self.into_source())
+    }
+
+    /// Returns the only (EntityID, Item) of the join, or a SingleError if no entities or several entities match.
+    /// This is meant for components that only one entity should have, such as a MainCamera.
+    fn single(self) -> Result<<JoinIter<Self::Source> as Iterator>::Item, SingleError> {
+        let mut iter = self.join();
+        match iter.next() {
+            Some(first) => match iter.count() {
+                0 => Ok(first),
+                rest => Err(SingleError::MultipleEntities(std::any::type_name::<C>(), rest + 1)),
+            },
+            None => Err(SingleError::NoEntities(std::any::type_name::<C>())),
+        }
+    }
+}
+
+/// The error returned by Join::single, the str is the name of the joined components
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SingleError {
+    NoEntities(&'static str),               // No entity had the components
+    MultipleEntities(&'static str, usize),  // The number of entities that had the components
+}
+
+impl fmt::Display for SingleError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SingleError::NoEntities(name) => write!(f, "Expected exactly one entity with {} but there were none", name),
+            SingleError::MultipleEntities(name, count) => write!(f, "Expected exactly one entity with {} but there were {}", name, count),
+        }
+    }
+}
+
+impl std::error::Error for SingleError {}
+
+/// The next EntityID that a JoinSource could yield
+pub enum Candidate<K> {
+    Key(K),     // The smallest EntityID the source can still yield
+    Any,        // The source doesn't restrict which EntityIDs are yielded, such as Optional and Not
+    End,        // The source has no more EntityIDs
+}
+
+/// The result of probing a JoinSource for a particular EntityID
+pub enum Probe<K> {
+    Hit,        // The source has the EntityID and can yield it
+    Ahead(K),   // The source does not have the EntityID, the next EntityID it has is K
+    Miss,       // The source rejects the EntityID, but doesn't know the next EntityID it would accept
+    End,        // The source has no EntityIDs greater or equal to the probed EntityID
+}
+
+/// A JoinSource is a sorted stream of (EntityID, Item) pairs that can be merged with other
+/// JoinSources. Users should not need to implement this trait as it is implemented for all
+/// ComponentStorages, however it is public so that custom join operators can be written.
+pub trait JoinSource {
+    type EntityID : Ord + Copy;
+    type Item;
+
+    /// Returns the smallest EntityID this source can still yield
+    fn candidate(&mut self) -> Candidate<Self::EntityID>;
+
+    /// Advances the source to the given EntityID and reports whether or not the source has it
+    fn probe(&mut self, key : Self::EntityID) -> Probe<Self::EntityID>;
+
+    /// Advances the source past the given EntityID, this is called when a Probe::Miss rejected the EntityID
+    fn skip(&mut self, key : Self::EntityID);
+
+    /// Takes the Item for the given EntityID, this must only be called after a Probe::Hit for the same key
+    fn take(&mut self, key : Self::EntityID) -> Self::Item;
+}
+
+/// JoinIter is an iterator that merges the EntityIDs of a JoinSource
+/// and yields (EntityID, Item) for each EntityID every source agrees on. A join where every storage is wrapped
+/// in Optional, Not or Lookup has no storage to take the EntityIDs from, so it yields nothing
+pub struct JoinIter<J>(J);
+
+impl<J : JoinSource> Iterator for JoinIter<J> {
+    type Item = (J::EntityID, J::Item);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // The candidate is the largest of the smallest EntityIDs of each source
+            let key = match self.0.candidate() {
+                Candidate::Key(key) => key,
+                Candidate::Any | Candidate::End => return None,
+            };
+            match self.0.probe(key) {
+                Probe::Hit => return Some((key, self.0.take(key))),
+                // One of the sources skipped past the key, so get a new candidate
+                Probe::Ahead(_) => continue,
+                // One of the sources rejected the key, so move past it
+                Probe::Miss => self.0.skip(key),
+                Probe::End => return None,
+            }
+        }
+    }
+}
+
+/// StorageSource wraps the sorted iterator of a ComponentStorage, it keeps the
+/// last pulled (EntityID, Component) pair around so that it can be compared against other sources
+pub struct StorageSource<K, T, I : Iterator<Item=(K, T)>> {
+    iter: Fuse<I>,
+    head: Option<(K, T)>,
+}
+
+impl<K : Ord + Copy, T, I : Iterator<Item=(K, T)>> StorageSource<K, T, I> {
+    /// Creates a new StorageSource from an iterator sorted by EntityID
+    pub fn new(iter : I) -> Self {
+        Self {
+            iter: iter.fuse(),
+            head: None,
+        }
+    }
+
+    /// Returns the EntityID at the head of the source, pulling a new pair from the iterator if needed
+    fn peek(&mut self) -> Option<K> {
+        if self.head.is_none() {
+            self.head = self.iter.next();
+        }
+        self.head.as_ref().map(|(k, _)| *k)
+    }
+
+    /// Discards pairs until the head of the source is greater or equal to the given EntityID
+    fn seek(&mut self, key : K) -> Option<K> {
+        while let Some(k) = self.peek() {
+            if k >= key {
+                return Some(k);
+            }
+            self.head = None;
+        }
+        None
+    }
+}
+
+impl<K : Ord + Copy, T, I : Iterator<Item=(K, T)>> JoinSource for StorageSource<K, T, I> {
+    type EntityID = K;
+    type Item = T;
+    fn candidate(&mut self) -> Candidate<K> {
+        match self.peek() {
+            Some(k) => Candidate::Key(k),
+            None => Candidate::End,
+        }
+    }
+    fn probe(&mut self, key : K) -> Probe<K> {
+        match self.seek(key) {
+            Some(k) if k == key => Probe::Hit,
+            Some(k) => Probe::Ahead(k),
+            None => Probe::End,
+        }
+    }
+    fn skip(&mut self, key : K) {
+        if self.seek(key) == Some(key) {
+            self.head = None;
+        }
+    }
+    fn take(&mut self, _key : K) -> T {
+        match self.head.take() {
+            Some((_, value)) => value,
+            None => unreachable!(),
+        }
+    }
+}
+
+/// Joining a refrence to a ComponentStorage yields refrences to its components
+impl<'a, C : 'a, S : ComponentStorage<C>> Join<C> for &'a S where S::EntityID : Ord + Copy {
+    type Source = StorageSource<S::EntityID, &'a C, S::Iter<'a>>;
+    fn into_source(self) -> Self::Source { StorageSource::new(self.component_iter()) }
+}
+
+/// Joining a mutable refrence to a ComponentStorage yields mutable refrences to its components
+impl<'a, C : 'a, S : ComponentStorage<C>> Join<C> for &'a mut S where S::EntityID : Ord + Copy {
+    type Source = StorageSource<S::EntityID, &'a mut C, S::IterMut<'a>>;
+    fn into_source(self) -> Self::Source { StorageSource::new(self.component_iter_mut()) }
+}
+
+/// Optional wraps a Join so that entities without the component are still yielded,
+/// the component is yielded as an Option that is None when the entity doesn't have it
+pub struct Optional<J>(pub J);
+
+/// OptionalSource is the JoinSource of Optional, it accepts every EntityID
+pub struct OptionalSource<S> {
+    inner: S,
+    found: bool,
+}
+
+impl<S : JoinSource> JoinSource for OptionalSource<S> {
+    type EntityID = S::EntityID;
+    type Item = Option<S::Item>;
+    fn candidate(&mut self) -> Candidate<S::EntityID> { Candidate::Any }
+    fn probe(&mut self, key : S::EntityID) -> Probe<S::EntityID> {
+        // remember whether the inner source had the key, as it determines what take returns
+        self.found = matches!(self.inner.probe(key), Probe::Hit);
+        Probe::Hit
+    }
+    fn skip(&mut self, _key : S::EntityID) {}
+    fn take(&mut self, key : S::EntityID) -> Option<S::Item> {
+        if self.found { Some(self.inner.take(key)) } else { None }
+    }
+}
+
+impl<C, J : Join<C>> Join<Option<C>> for Optional<J> {
+    type Source = OptionalSource<J::Source>;
+    fn into_source(self) -> Self::Source {
+        OptionalSource {
+            inner: self.0.into_source(),
+            found: false,
+        }
+    }
+}
+
+/// Not wraps a Join so that entities with the component are skipped, it yields ()
+pub struct Not<J>(pub J);
+
+/// NotSource is the JoinSource of Not, it rejects every EntityID the inner source has
+pub struct NotSource<S>(S);
+
+impl<S : JoinSource> JoinSource for NotSource<S> {
+    type EntityID = S::EntityID;
+    type Item = ();
+    fn candidate(&mut self) -> Candidate<S::EntityID> { Candidate::Any }
+    fn probe(&mut self, key : S::EntityID) -> Probe<S::EntityID> {
+        match self.0.probe(key) {
+            Probe::Hit => Probe::Miss,
+            _ => Probe::Hit,
+        }
+    }
+    fn skip(&mut self, _key : S::EntityID) {}
+    fn take(&mut self, _key : S::EntityID) {}
+}
+
+impl<C, J : Join<C>> Join<Not<C>> for Not<J> {
+    type Source = NotSource<J::Source>;
+    fn into_source(self) -> Self::Source { NotSource(self.0.into_source()) }
+}
+
+/// Lookup wraps a refrence to a ComponentStorage so that it is probed with component_get instead of being iterated.
+/// The other storages of the join decide which entities are visited, and each one is looked up in the wrapped storage,
+/// skipping the entities that aren't in it. This suits storages that can't iterate in order cheaply, such as a HashMap,
+/// when one of the other storages has fewer components. A join needs atleast one storage that isn't wrapped, otherwise it yields nothing.
+/// Only a shared refrence can be wrapped, as nothing guarantees that component_get_mut returns a different component
+/// for each EntityID, so the components looked up can't be mutated through the join.
+pub struct Lookup<J>(pub J);
+
+/// LookupSource is the JoinSource of Lookup, it accepts the EntityIDs the storage has a component for
+pub struct LookupSource<S, C> {
+    storage: S,
+    _component: PhantomData<fn() -> C>,
+}
+
+impl<'a, C : 'a, S : ComponentStorage<C>> JoinSource for LookupSource<&'a S, C> where S::EntityID : Ord + Copy {
+    type EntityID = S::EntityID;
+    type Item = &'a C;
+    fn candidate(&mut self) -> Candidate<S::EntityID> { Candidate::Any }
+    fn probe(&mut self, key : S::EntityID) -> Probe<S::EntityID> {
+        if self.storage.component_get(key).is_some() { Probe::Hit } else { Probe::Miss }
+    }
+    fn skip(&mut self, _key : S::EntityID) {}
+    fn take(&mut self, key : S::EntityID) -> &'a C {
+        match self.storage.component_get(key) {
+            Some(value) => value,
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<'a, C : 'a, S : ComponentStorage<C>> Join<C> for Lookup<&'a S> where S::EntityID : Ord + Copy {
+    type Source = LookupSource<&'a S, C>;
+    fn into_source(self) -> Self::Source { LookupSource { storage: self.0, _component: PhantomData } }
+}
+
+/// Reborrow is implemented on the refrences held by a Guard, along with tuples of them.
+/// It allows the 'static refrences created by acquire! to be borrowed for the lifetime of the Guard
+/// so that Guard::join can be called directly.
+pub trait Reborrow<'a> {
+    type Output;
+    fn reborrow(&'a mut self) -> Self::Output;
+}
+
+impl<'a, 'b : 'a, S : 'a> Reborrow<'a> for &'b S {
+    type Output = &'a S;
+    fn reborrow(&'a mut self) -> &'a S { self }
+}
+
+impl<'a, 'b : 'a, S : 'a> Reborrow<'a> for &'b mut S {
+    type Output = &'a mut S;
+    fn reborrow(&'a mut self) -> &'a mut S { self }
+}
+
+/// impl_join_tuple! implements Join, JoinSource and Reborrow on a tuple
+/// each argument is the type parameter of the element, the component parameter, and its index
+macro_rules! impl_join_tuple {
+    ($($t:ident $c:ident $i:tt),*) => {
+        impl<K : Ord + Copy, $($t : JoinSource<EntityID=K>),*> JoinSource for ($($t,)*) {
+            type EntityID = K;
+            type Item = ($($t::Item,)*);
+            fn candidate(&mut self) -> Candidate<K> {
+                // The candidate is the largest of the candidates, as no EntityID below it can be in every source
+                let mut max = Candidate::Any;
+                $(
+                    match (self.$i.candidate(), &max) {
+                        (Candidate::End, _) => return Candidate::End,
+                        (Candidate::Key(key), Candidate::Key(m)) if key <= *m => (),
+                        (Candidate::Key(key), _) => max = Candidate::Key(key),
+                        (Candidate::Any, _) => (),
+                    }
+                )*
+                max
+            }
+            fn probe(&mut self, key : K) -> Probe<K> {
+                let mut result = Probe::Hit;
+                $(
+                    match self.$i.probe(key) {
+                        Probe::Hit => (),
+                        Probe::Ahead(k) => result = match result {
+                            Probe::Ahead(other) if other > k => Probe::Ahead(other),
+                            _ => Probe::Ahead(k),
+                        },
+                        // a source that is ahead has already moved past the key, so it takes priority over a miss
+                        Probe::Miss => if let Probe::Hit = result {
+                            result = Probe::Miss;
+                        },
+                        Probe::End => return Probe::End,
+                    }
+                )*
+                result
+            }
+            fn skip(&mut self, key : K) {
+                $(self.$i.skip(key);)*
+            }
+            fn take(&mut self, key : K) -> Self::Item {
+                ($(self.$i.take(key),)*)
+            }
+        }
+
+        impl<$($c, $t : Join<$c>),*> Join<($($c,)*)> for ($($t,)*) where ($($t::Source,)*) : JoinSource {
+            type Source = ($($t::Source,)*);
+            fn into_source(self) -> Self::Source {
+                ($(self.$i.into_source(),)*)
+            }
+        }
+
+        impl<'a, $($t : Reborrow<'a>),*> Reborrow<'a> for ($($t,)*) {
+            type Output = ($($t::Output,)*);
+            fn reborrow(&'a mut self) -> Self::Output {
+                ($(self.$i.reborrow(),)*)
+            }
+        }
+    };
+}
+
+impl_join_tuple!(A CA 0);
+impl_join_tuple!(A CA 0, B CB 1);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3, F CF 4);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3, F CF 4, G CG 5);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3, F CF 4, G CG 5, H CH 6);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3, F CF 4, G CG 5, H CH 6, I CI 7);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3, F CF 4, G CG 5, H CH 6, I CI 7, J CJ 8);
+impl_join_tuple!(A CA 0, B CB 1, D CD 2, E CE 3, F CF 4, G CG 5, H CH 6, I CI 7, J CJ 8, L CL 9);
@@ -1,6 +1,14 @@
+// The exported macros refer to the crate as genecs, this allows them to be used inside of the crate
+extern crate self as genecs;
+#[macro_use] pub mod static_storage;
 pub mod entity;
 #[macro_use] pub mod component;
+pub mod join;
+pub mod storage;
 #[macro_use] pub mod system;
+pub mod dispatcher;
+pub mod commands;
+pub mod thread_pool;
 #[macro_use] pub mod resource;
-#[macro_use] pub mod static_storage;
-pub extern crate crossbeam_utils;
\ No newline at end of file
+#[macro_use] pub mod events;
+pub mod world;
\ No newline at end of file
@@ -13,25 +13,34 @@ pub trait Resource : StaticStorage<Self> + 'static {}
 #[macro_export] macro_rules! impl_resource {
     ($name:ty, $init:expr) => {
         impl genecs::static_storage::StaticStorage<$name> for $name {
-            unsafe fn get_static() -> &'static mut ($name, (u64, u64)) {
-                // Import std::sync::Once and std::sync::ONCE_INIT as the user might not import themselves
-                use std::sync::{Once, ONCE_INIT};
-                // Use an Inner type to call ::defualt as types with generic arguments will cause an error
-                type Storage = $name;
+            fn init() -> $name {
+                $init
+            }
+            unsafe fn get_static() -> &'static mut $name {
+                // Import std::sync::Once as the user might not import it themselves
+                use std::sync::Once;
                 // Initialize the static storage to a const (Option, Once)
-                static mut STATIC_STORAGE : (Option<($name, (u64, u64))>, Once) = (None, ONCE_INIT);
-                // At runtime Change the static storages None to a Some($storage, (u64, u64))
-                STATIC_STORAGE.1.call_once(|| {
-                    STATIC_STORAGE.0 = Some(($init, (0,0)));
+                static mut STATIC_STORAGE : (Option<$name>, Once) = (None, Once::new());
+                // At runtime Change the static storages None to a Some($storage)
+                (*std::ptr::addr_of!(STATIC_STORAGE.1)).call_once(|| {
+                    STATIC_STORAGE.0 = Some(<Self as genecs::static_storage::StaticStorage<$name>>::init());
                 });
                 // Coerce a mutable refrence into a mutable pointer
-                let ptr = &mut STATIC_STORAGE.0 as * mut Option<($name, (u64, u64))>;
+                let ptr = std::ptr::addr_of_mut!(STATIC_STORAGE.0);
                 // Unwrap the option and return the inner storage
                 match *ptr {
                     Some(ref mut x) => x,
                     None => unreachable!(),
                 }
             }
+            fn get_lock() -> &'static genecs::static_storage::StorageLock {
+                // The lock is const created, so unlike the storage it doesn't need to be initialized at runtime
+                static LOCK : genecs::static_storage::StorageLock = genecs::static_storage::StorageLock::new();
+                &LOCK
+            }
+        }
+        impl genecs::static_storage::Stored for $name {
+            type Storage = $name;
         }
         impl Resource for $name {}
     }
@@ -1,165 +1,420 @@
-use std::sync::{Mutex, Condvar, Once, ONCE_INIT};
+use std::sync::{Mutex, Condvar, PoisonError};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use crate::join::{Join, JoinIter, Reborrow};
+use crate::entity::EntityID;
 
 /// The StaticStorage trait is used as a building block for other types in the crate
-/// StaticStorage contains a static of type Storage and also keeps track of the number
-/// of readers and writers to it's contents. It also contains a mutex that can be used to
-/// guarentee mutual exclusion to it's contents, however this needs to be manually handled by the
-/// user.
+/// StaticStorage contains a static of type Storage and a StorageLock that keeps track of the number
+/// of readers and writers to it's contents. Every Storage has it's own lock, so acquiring one
+/// Storage never has to wait on a thread that is using a different Storage.
 pub trait StaticStorage<Storage : 'static> : Sized {
     /// read is an unsafe function that shouldn't be called directly. 
     /// This function retrieves the storage, and returns a refrence of it if there are currently no writers.
     /// An Error::IllegalReadGuardAcquire is returned if there is currently a writer in the storage.
-    /// An Error::UnitializedStorage is returned if the storage hasn't been initialized.
-    /// This function is NOT thread safe.
     /// This function does not block.
     /// 
-    /// This function is used by the acquire! macro.
+    /// # Safety
+    /// The reader must be released with release_read_guard once the refrence is no longer used.
     unsafe fn read() -> Result<&'static Storage,Error> {
-        let store = Self::get_static();
-        match store {
-            // So long as there are no writers, you can create a reader
-            (storage,(i,0)) => {
-                *i += 1; // Increment the number of readers
-                return Ok(storage);
-            },
-            _ => Err(Error::IllegalReadGuardAcquire),
-        }
+        Self::get_lock().try_read()?;
+        Ok(Self::get_static())
     }
 
     /// write is an unsafe function that shouldn't be called directly. 
     /// This function retrieves the storage, and returns a mutable refrence of it if there are currently no readers or writers.
     /// An Error::IllegalWriteGuardAcquire is returned if there is currently a writer or reader in the storage.
-    /// An Error::UnitializedStorage is returned if the storage hasn't been initialized.
-    /// This function is NOT thread safe.
     /// This function does not block.
     /// 
-    /// This function is used by the acquire! macro.
+    /// # Safety
+    /// The writer must be released with release_write_guard once the refrence is no longer used.
     unsafe fn write() -> Result<&'static mut Storage,Error> {
-        let mut store = Self::get_static();
-        match store {
-            // So long as there are no readers or writers, you can create a writer
-            (storage,(0,0)) => {
-                (store.1).1 = 1; // Set the number of writers to 1
-                return Ok(storage);
-            },
-            _ => Err(Error::IllegalWriteGuardAcquire),
-        }
+        Self::get_lock().try_write()?;
+        Ok(Self::get_static())
     }
     
     /// release_read_guard is an unsafe function that shouldn't be called directly.
     /// This function simply decrements the number of readers being tracked by the storage.
     /// This function does not actually guarentee that the read_guard is actually freed.
-    /// An Error::AliasingDetected is returned if there is a reader and a writer, or multiple writers.
+    /// An Error::AliasingDetected is returned if there is a writer.
     /// An Error::UnacountedGuard is returned if the storage didn't have readers to free.
-    /// This function is NOT thread safe.
     /// This function does not block.
     /// 
     /// This function is used in the desturctor of a Guard that is generated by the acquire! macro.
+    /// 
+    /// # Safety
+    /// The refrence returned by read must no longer be used.
     unsafe fn release_read_guard() -> Result<(), Error>{
-        let store = Self::get_static();
-        match store {
-            // If the number of readers is 0, then releasing a reader is an UnacountedGuard Error
-            (_, (0, 0)) => return Err(Error::UnacountedGuard),
-            // If the number of readers is greater than 0 and there are 0 writers then decrement number of readers
-            (_, (r, 0)) => *r -= 1,
-            // If there was a writer, then aliasing has occured, return an AliasingDetected error
-            _ => return Err(Error::AliasingDetected),
-        }
-        Ok(())
+        Self::get_lock().release_read()
     }
 
     /// release_write_guard is an unsafe function that shouldn't be called directly.
     /// This function simply decrements the number of writers being tracked by the storage.
     /// This function does not actually guarentee that the write_guard is actually freed.
-    /// An Error::AliasingDetected is returned if there is a reader and a writer, or multiple writers.
+    /// An Error::AliasingDetected is returned if there are readers.
     /// An Error::UnacountedGuard is returned if the storage didn't have a writer to free.
-    /// This function is NOT thread safe.
     /// This function does not block.
     /// 
     /// This function is used in the desturctor of a Guard that is generated by the acquire! macro.
+    /// 
+    /// # Safety
+    /// The refrence returned by write must no longer be used.
     unsafe fn release_write_guard() -> Result<(), Error> {
-        let store = Self::get_static();
-        match store {
-            // If there is 1 writer and 0 readers then decrement the number of writers
-            (_, (0, w)) if *w == 1 => *w -= 1,
-            // If there is 0 writers than releasing a writer is an UnacountedGuard error
-            (_, (0, 0)) => return Err(Error::UnacountedGuard),
-            // If there are readers or multiple writers then return an AliasingDetected error
-            _ => return Err(Error::AliasingDetected),
-        }
-        Ok(())
+        Self::get_lock().release_write()
     }
     
-    /// get_access is an unsafe function that returns the number of readers and the number of writers that
-    /// are currently acessing the Storage.
-    /// This function is NOT thread safe.
-    /// This function does not block.
-    /// 
-    /// This function is used by the acquire! macro
-    unsafe fn get_access() -> RWInfo { Self::get_static().1 }
+    /// get_access returns the number of readers and the number of writers that
+    /// are currently acessing the Storage. Other threads can change this at any time.
+    fn get_access() -> RWInfo { Self::get_lock().get_access() }
 
     /// Since rust does not support associated statics (yet) this function needs to be implemented by the user. This function points to the static
     /// Storage. users can use the impl_component! or impl_resource! macro which will create the static Storage and implement this function. This function
     /// is unsafe because getting a mutable refrence to a static variable is unsafe.
-    unsafe fn get_static() -> &'static mut (Storage, RWInfo);
+    /// 
+    /// # Safety
+    /// The returned refrence must only be used while the reader or writer is accounted for in the StorageLock from get_lock.
+    unsafe fn get_static() -> &'static mut Storage;
 
-    /// The MUTEX contains both a mutex and a condition variable, there is one of these per program
-    /// and it is used to ensure mutual exclusion when accessing the Storages
-    fn get_mutex() -> &'static (Mutex<()>,Condvar) {
-        unsafe {
-            // create the static variable, but initialize it to None, because Mutex and Condvar can't be const created
-            static mut MUTEX : (Option<(Mutex<()>,Condvar)>, Once) = (None, ONCE_INIT);
-            // Using the sync::Once initialize the Mutex and the Condvar to ensure this only happens once
-            MUTEX.1.call_once(|| {
-                MUTEX.0 = Some((Mutex::new(()), Condvar::new()));
-            });
-            // Coerce a mutable refrence into a mutable pointer
-            let ptr = &mut MUTEX.0 as * mut Option<(Mutex<()>,Condvar)>;
-            // Match the ptr to unwrap the option, then return the inner value
-            match *ptr {
-                Some(ref x) => x,
-                None => unreachable!(),
-            }
-        }
+    /// get_lock returns the StorageLock that keeps track of the readers and writers of the static Storage.
+    /// This function is implemented by the impl_component! and impl_resource! macros.
+    fn get_lock() -> &'static StorageLock;
+
+    /// init returns the initial value of the Storage, for components this is the Default of the
+    /// ComponentStorage and for resources it is the expression given to impl_resource!.
+    /// This function is implemented by the impl_component! and impl_resource! macros.
+    fn init() -> Storage;
+
+    /// despawn removes an entity from the Storage if the Storage is keyed by EntityID, this is
+    /// implemented by the impl_component! macro. By default it does nothing, as is the case for resources.
+    fn despawn(_storage : &mut Storage, _id : EntityID) {}
+
+    /// reset replaces the Storage with it's initial value from init, the Storage is acquired for
+    /// writing the same way as acquire! so this function blocks until there are no other readers or writers.
+    /// The reset! macro can be used to call this function without importing StaticStorage.
+    /// The on_remove hooks of a Component aren't called for the components that are dropped.
+    fn reset() where Self : 'static {
+        let mut guard = crate::acquire!(Write(Self));
+        let (storage,) = guard.get();
+        **storage = Self::init();
     }
 }
 
+/// Stored names the Storage of a StaticStorage, so that the Storage can be found from the type alone.
+/// This is used by SystemData, where Read<T> and Write<T> only know the type T.
+/// This trait is implemented by the impl_component! and impl_resource! macros.
+pub trait Stored {
+    type Storage : 'static;
+}
+
 /// RWInfo contains the number of readers and the number of writers currently accessing the Storage
 type RWInfo = (u64, u64);
 
+/// The value of the StorageLock state when the Storage has been loaned to a writer
+const WRITER : u64 = u64::MAX;
+
+/// Access is the kind of refrence requested from a StorageLock
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// A StorageLock keeps track of the readers and writers of a single Storage. The number of readers
+/// and writers is kept in an atomic so that acquiring and releasing a Storage never takes a lock,
+/// the mutex and condition variable are only used by threads that need to sleep until the Storage
+/// is available. Releasing a Storage only wakes up the threads waiting on that Storage, and only
+/// when there is a thread waiting.
+pub struct StorageLock {
+    state: AtomicU64,           // The number of readers, or WRITER if there is a writer
+    waiters: AtomicUsize,       // The number of threads sleeping on the condition variable
+    mutex: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl StorageLock {
+    /// Creates a StorageLock with no readers or writers
+    pub const fn new() -> Self {
+        StorageLock {
+            state: AtomicU64::new(0),
+            waiters: AtomicUsize::new(0),
+            mutex: Mutex::new(()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Increments the number of readers if there is no writer.
+    /// An Error::IllegalReadGuardAcquire is returned if there is currently a writer.
+    pub fn try_read(&self) -> Result<(), Error> {
+        let mut state = self.state.load(Ordering::SeqCst);
+        loop {
+            if state == WRITER || state == WRITER - 1 {
+                return Err(Error::IllegalReadGuardAcquire);
+            }
+            match self.state.compare_exchange_weak(state, state + 1, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return Ok(()),
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Sets the writer if there are no readers or writers.
+    /// An Error::IllegalWriteGuardAcquire is returned if there is currently a writer or reader.
+    pub fn try_write(&self) -> Result<(), Error> {
+        self.state.compare_exchange(0, WRITER, Ordering::SeqCst, Ordering::SeqCst)
+            .map(|_| ())
+            .map_err(|_| Error::IllegalWriteGuardAcquire)
+    }
+
+    /// Calls try_read or try_write depending on the access
+    pub fn try_acquire(&self, access : Access) -> Result<(), Error> {
+        match access {
+            Access::Read => self.try_read(),
+            Access::Write => self.try_write(),
+        }
+    }
+
+    /// Decrements the number of readers, waking up the threads waiting on this lock once there are no readers left.
+    /// An Error::AliasingDetected is returned if there is a writer.
+    /// An Error::UnacountedGuard is returned if there are no readers to free.
+    pub fn release_read(&self) -> Result<(), Error> {
+        let mut state = self.state.load(Ordering::SeqCst);
+        loop {
+            match state {
+                0 => return Err(Error::UnacountedGuard),
+                WRITER => return Err(Error::AliasingDetected),
+                _ => (),
+            }
+            match self.state.compare_exchange_weak(state, state - 1, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => break,
+                Err(current) => state = current,
+            }
+        }
+        // Only writers wait on readers, so there is no one to wake up until the last reader is gone
+        if state == 1 {
+            self.notify();
+        }
+        Ok(())
+    }
+
+    /// Removes the writer, waking up the threads waiting on this lock.
+    /// An Error::AliasingDetected is returned if there are readers.
+    /// An Error::UnacountedGuard is returned if there is no writer to free.
+    pub fn release_write(&self) -> Result<(), Error> {
+        match self.state.compare_exchange(WRITER, 0, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => {
+                self.notify();
+                Ok(())
+            },
+            Err(0) => Err(Error::UnacountedGuard),
+            Err(_) => Err(Error::AliasingDetected),
+        }
+    }
+
+    /// Calls release_read or release_write depending on the access
+    pub fn release(&self, access : Access) -> Result<(), Error> {
+        match access {
+            Access::Read => self.release_read(),
+            Access::Write => self.release_write(),
+        }
+    }
+
+    /// Returns true if the access could currently be acquired, other threads can change this at any time
+    pub fn is_available(&self, access : Access) -> bool {
+        match (access, self.state.load(Ordering::SeqCst)) {
+            (Access::Read, state) => state < WRITER - 1,
+            (Access::Write, state) => state == 0,
+        }
+    }
+
+    /// Returns the number of readers and the number of writers, other threads can change this at any time
+    pub fn get_access(&self) -> RWInfo {
+        match self.state.load(Ordering::SeqCst) {
+            WRITER => (0, 1),
+            readers => (readers, 0),
+        }
+    }
+
+    /// Sleeps until the access is available, this doesn't acquire it so the access
+    /// may be taken by another thread by the time this returns
+    pub fn wait(&self, access : Access) {
+        let mut mutex = self.mutex.lock().expect("Failed to access Storage mutex");
+        // The waiter is counted before checking the state so that a release either happens before
+        // the check, or sees the waiter and notifies it after this thread is asleep
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        while !self.is_available(access) {
+            mutex = self.condvar.wait(mutex).expect("Failed to access Storage mutex");
+        }
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Sleeps until the access is available or the timeout has passed, returning whether the access is available.
+    /// Like wait this doesn't acquire the access.
+    pub fn wait_timeout(&self, access : Access, timeout : Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut mutex = self.mutex.lock().expect("Failed to access Storage mutex");
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        let available = loop {
+            let now = Instant::now();
+            if self.is_available(access) {
+                break true;
+            } else if now >= deadline {
+                break false;
+            }
+            mutex = self.condvar.wait_timeout(mutex, deadline - now).expect("Failed to access Storage mutex").0;
+        };
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+        available
+    }
+
+    /// Wakes up the threads waiting on this lock if there are any
+    fn notify(&self) {
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            // Holding the mutex ensures a waiter is either asleep or hasn't checked the state yet.
+            // This is called while releasing a Guard, so a poisoned mutex is used anyways rather than panicking
+            let _mutex = self.mutex.lock().unwrap_or_else(PoisonError::into_inner);
+            self.condvar.notify_all();
+        }
+    }
+}
+
+impl Default for StorageLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// acquire_locks blocks until every lock can be acquired with it's access at the same time, it is used
+/// by the acquire! macro. The locks are tried in the order of their address so that every thread tries
+/// them in the same order. If a lock can't be acquired the locks that were already acquired are released
+/// and the thread sleeps until that lock is available, so a thread never holds onto some of it's storages
+/// while waiting on another. A storage can be read multiple times, but written to at most once.
+/// An Error::AliasingDetected is returned if a storage is requested more than once and one of the requests is a write,
+/// as those locks could never be acquired together. Otherwise an Error is only returned if releasing the locks that
+/// were already acquired fails.
+pub fn acquire_locks(locks : &mut [(&StorageLock, Access)]) -> Result<(), Error> {
+    sort_locks(locks)?;
+    while let Some(i) = acquire_all(locks)? {
+        let (lock, access) = locks[i];
+        lock.wait(access);
+    }
+    Ok(())
+}
+
+/// try_acquire_locks acquires every lock with it's access if they are all available, otherwise none of them
+/// are acquired and the Error of the first lock that wasn't available is returned. This function does not block.
+/// It is used by the try_acquire! macro.
+pub fn try_acquire_locks(locks : &mut [(&StorageLock, Access)]) -> Result<(), Error> {
+    sort_locks(locks)?;
+    match acquire_all(locks)? {
+        Some(i) => Err(unavailable(locks[i].1)),
+        None => Ok(()),
+    }
+}
+
+/// acquire_locks_timeout is acquire_locks, but gives up once the timeout has passed. When it gives up none of
+/// the locks are acquired and the Error of the lock that wasn't available is returned. It is used by the acquire_timeout! macro.
+pub fn acquire_locks_timeout(locks : &mut [(&StorageLock, Access)], timeout : Duration) -> Result<(), Error> {
+    let deadline = Instant::now() + timeout;
+    sort_locks(locks)?;
+    while let Some(i) = acquire_all(locks)? {
+        let (lock, access) = locks[i];
+        let now = Instant::now();
+        if now >= deadline || !lock.wait_timeout(access, deadline - now) {
+            return Err(unavailable(access));
+        }
+    }
+    Ok(())
+}
+
+/// Sorts the locks by address so that every thread tries to acquire them in the same order.
+/// An Error::AliasingDetected is returned if the same lock is requested twice and either request is a write.
+fn sort_locks(locks : &mut [(&StorageLock, Access)]) -> Result<(), Error> {
+    locks.sort_by_key(|(lock, _)| *lock as *const StorageLock as usize);
+    for pair in locks.windows(2) {
+        let ((first, first_access), (second, second_access)) = (pair[0], pair[1]);
+        if std::ptr::eq(first, second) && (first_access == Access::Write || second_access == Access::Write) {
+            return Err(Error::AliasingDetected);
+        }
+    }
+    Ok(())
+}
+
+/// Tries to acquire every lock, if one isn't available the locks that were already acquired are released
+/// and the index of the lock that wasn't available is returned. An Error is returned if releasing fails.
+fn acquire_all(locks : &[(&StorageLock, Access)]) -> Result<Option<usize>, Error> {
+    for (i, (lock, access)) in locks.iter().enumerate() {
+        if lock.try_acquire(*access).is_err() {
+            // Release every lock before returning the first error
+            let mut result = Ok(());
+            for (acquired, access) in locks[..i].iter() {
+                result = result.and(acquired.release(*access));
+            }
+            result?;
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+/// The Error returned when the access isn't available
+fn unavailable(access : Access) -> Error {
+    match access {
+        Access::Read => Error::IllegalReadGuardAcquire,
+        Access::Write => Error::IllegalWriteGuardAcquire,
+    }
+}
+
 /// Guard takes a tuple of refrences to StaticStorages T
 /// and a desturctor F that frees all the resources in the tuple
 /// T. This should be created using the acquire! macro
-pub struct Guard<T, F : FnMut()>{
+pub struct Guard<T, F : FnMut() -> Result<(), Error>>{
     storages: T,
     destructor: F,
+    released: bool,
 }
 
 
-impl<T, F : FnMut()> Guard<T, F>{
+impl<T, F : FnMut() -> Result<(), Error>> Guard<T, F>{
     /// Creates a new Guard
     pub fn new(t : T, f : F) -> Self {
         Self {
             storages : t,
-            destructor : f
+            destructor : f,
+            released : false,
         }
     }
     /// gets the underlining resources protected by the guard
     pub fn get(&mut self) -> &mut T {
         &mut self.storages
     }
+    /// joins all of the resources protected by the guard, yielding the EntityID and
+    /// the components of every entity that is in each of the storages
+    pub fn join<'a, C>(&'a mut self) -> JoinIter<<T::Output as Join<C>>::Source> where T : Reborrow<'a>, T::Output : Join<C> {
+        self.storages.reborrow().join()
+    }
+    /// release frees all of the resources protected by the guard, the same as dropping it, but
+    /// returns the first Error that occured while releasing them. Every resource is released even
+    /// if releasing one of them fails.
+    pub fn release(mut self) -> Result<(), Error> {
+        self.released = true;
+        (self.destructor)()
+    }
 }
 
 /// Call the destructor function on drop to release all the resources
-/// aquired by the guard at creation
-impl<T, F : FnMut()> Drop for Guard<T, F> {
+/// aquired by the guard at creation. Panicking in drop would abort the program
+/// if it was already unwinding, so errors are ignored here, use Guard::release to get them.
+impl<T, F : FnMut() -> Result<(), Error>> Drop for Guard<T, F> {
     fn drop(&mut self) {
-        (self.destructor)();
+        if !self.released {
+            let _ = (self.destructor)();
+        }
     }
 }
 
 /// An Enum that represents all the different types of errors
 /// that can be generated by Static Storage
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Error{
     AliasingDetected,           // Occurs when readers and writers are loaned at the same time
     UnacountedGuard,            // Occurs when you try to free a guard, but the rw_info says the guard doesn't exist
@@ -167,7 +422,7 @@ pub enum Error{
     IllegalWriteGuardAcquire,   // Occurs when you try to gain write access with readers or writers already loaned
 }
 
-impl std::fmt::Debug for Error {
+impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::AliasingDetected => write!(f, "Both read and write refrences to a storage were detected"),
@@ -178,51 +433,118 @@ impl std::fmt::Debug for Error {
     }
 }
 
-/// can_aquire should not be used directly as it is unsafe, but it checks whether or not a stroage is available to a reader or writer
+impl std::fmt::Debug for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// can_aquire checks whether or not a stroage is currently available to a reader or writer, other threads can change this at any time
 #[macro_export] macro_rules! can_acquire {
     // In order for a mutable refrence to be valid, there can be no other mutable or immutable refrences out
     (Write($arg:tt)) => {
-        match $arg::get_access() {
-            (0,0) => true,
-            _ => false,
-        }
+        $arg::get_lock().is_available($crate::static_storage::Access::Write)
     };
     // In order for a refrence to be valid, there can be no other mutable refrences out
     (Read($arg:tt)) => {
-        match $arg::get_access() {
-            (_,0) => true,
-            _ => false,
-        }
+        $arg::get_lock().is_available($crate::static_storage::Access::Read)
+    };
+    // When a World is given, check the storage of the World instead of the static
+    ($world:ident, Write($arg:tt)) => {
+        $world.get_lock::<$arg, _>().is_available($crate::static_storage::Access::Write)
+    };
+    ($world:ident, Read($arg:tt)) => {
+        $world.get_lock::<$arg, _>().is_available($crate::static_storage::Access::Read)
     };
 }
 
-/// acquire_storage should not be used directly as it is unsafe, it simply calls write_storage or read_storage depending on whether Read(Struct) or Write(Struct) was passed
+/// acquire_storage should not be used directly as it is unsafe, it retrieves a mutable refrence or a refrence to the storage
+/// depending on whether Read(Struct) or Write(Struct) was passed. The lock of the storage must already be held.
 #[macro_export] macro_rules! acquire_storage {
     // If the Access was write then we want to acquire a mutable refrence
     (Write($arg:tt)) => {
-        $arg::write().unwrap()
+        $arg::get_static()
     };
     // If the Access was read then we want to acquire a refrence
     (Read($arg:tt)) => {
-        $arg::read().unwrap() 
+        &*$arg::get_static()
+    };
+    // When a World is given, retrieve the storage of the World instead of the static
+    ($world:ident, Write($arg:tt)) => {
+        $world.get_storage::<$arg, _>()
+    };
+    ($world:ident, Read($arg:tt)) => {
+        &*$world.get_storage::<$arg, _>()
     };
 }
 
-/// release_storage should not be used directly as it is unsafe and is used the the construction of the destructor of the Component Guard struct
+/// release_storage should not be used directly as it is unsafe and is used the the construction of the destructor of the Component Guard struct,
+/// it returns the Result of releasing the storage
 #[macro_export] macro_rules! release_storage {
     // If the Access was write then we want to release a write guard
     (Write($arg:tt)) => {
-        $arg::release_write_guard().unwrap() 
+        $arg::release_write_guard()
     };
     // If the Access was read then we want to release a read guard
     (Read($arg:tt)) => {
-        $arg::release_read_guard().unwrap()
+        $arg::release_read_guard()
+    };
+    // When a World is given, release the storage of the World instead of the static
+    ($world:ident, Write($arg:tt)) => {
+        $world.release_write::<$arg, _>()
+    };
+    ($world:ident, Read($arg:tt)) => {
+        $world.release_read::<$arg, _>()
     };
 }
 
 /// get_first retrieves the first type in a repetition
 #[macro_export] macro_rules! get_first {
-    ($type:tt, $(other:tt),*) => {$type};
+    ($type:tt, $($other:tt,)*) => {$type};
+}
+
+/// storage_locks should not be used directly, it creates an array of the StorageLock of each storage along with the requested access
+#[macro_export] macro_rules! storage_locks {
+    ($($access:tt($type:tt)),*) => {
+        [$(($type::get_lock(), $crate::static_storage::Access::$access),)*]
+    };
+    // When a World is given, collect the locks of the storages of the World, creating the storages that don't exist yet
+    ($world:ident => $($access:tt($type:tt)),*) => {
+        [$(($world.get_lock::<$type, _>(), $crate::static_storage::Access::$access),)*]
+    };
+}
+
+/// storage_guard should not be used directly as it is unsafe, it creates a Guard of the storages once their locks have been acquired
+#[macro_export] macro_rules! storage_guard {
+    ($($access:tt($type:tt)),*) => {
+        {
+            // Collect all the resources and store them in a tuple
+            let storages = ($($crate::acquire_storage!($access($type)),)*);
+            // In order to maintain rust's idomatic vibe of no manual destruction for the user,
+            // we create a lambda destructor that will be called by the created guard for freeing
+            // all the aquired types, releasing a storage wakes up the threads waiting on it
+            // Every storage is released before returning the first error
+            let destructor = || {
+                let results = [$($crate::release_storage!($access($type)),)*];
+                results.iter().copied().collect::<Result<(), $crate::static_storage::Error>>()
+            };
+            // create and return a Component Guard 
+            $crate::static_storage::Guard::new(storages, destructor)
+        }
+    };
+    // When a World is given, the refrences live as long as the World
+    ($world:ident => $($access:tt($type:tt)),*) => {
+        {
+            let refrences = ($($crate::acquire_storage!($world, $access($type)),)*);
+            let destructor = move || {
+                let results = [$($crate::release_storage!($world, $access($type)),)*];
+                results.iter().copied().collect::<Result<(), $crate::static_storage::Error>>()
+            };
+            $crate::static_storage::Guard::new(refrences, destructor)
+        }
+    };
 }
 
 /// The acquire! macro is used to retrieve an arbitrary number of StaticStorages
@@ -233,45 +555,143 @@ impl std::fmt::Debug for Error {
 /// ComponentGuard with refrences to the StaticStorages of Type1 and Type3, and
 /// a mutable refrence to the ComponentStorage of Type2. 
 /// 
+/// The storages of a World can be acquired by passing the World first, for example
+/// acquire!(world => Read(Type1), Write(Type2)) will return a Guard with refrences to
+/// the storages owned by world, rather than the static storages.
+/// 
+/// Each storage has it's own StorageLock, so a thread waiting on acquire! is only woken up
+/// when one of the storages it requested is released, see acquire_locks.
+/// 
 /// This macro CAN block
-/// This macro panics if an error occurs while acquiring or releasing resources
+/// This macro panics if an error occurs while acquiring resources, use acquire_checked! to get the Error instead.
+/// Errors that occur while releasing resources are ignored when the Guard is dropped, use Guard::release to get them.
 #[macro_export] macro_rules! acquire {
+    ($($args:tt)*) => {
+        match $crate::acquire_checked!($($args)*) {
+            Ok(guard) => guard,
+            Err(error) => panic!("Failed to acquire storages: {}", error),
+        }
+    };
+}
+
+/// The acquire_checked! macro is the same as acquire! but returns a Result<Guard, Error> instead of panicking
+/// when the accounting of a storage is wrong, such as an Error::AliasingDetected or Error::UnacountedGuard
+/// 
+/// This macro CAN block
+#[macro_export] macro_rules! acquire_checked {
     // capture an arbitrary number of arguments of the form Access(Type)
     ($($access:tt($type:tt)),*) => {
         unsafe {
-            use genecs::static_storage::{StaticStorage, Guard};
-            // get the type of the first type so that we can call ::get_mutex()
-            type first_type = get_first!($($type,)*);
-            // retrieve and lock the static storage mutex
-            let mut mutex = first_type::get_mutex().0.lock().expect("Failed to access Component mutex");
-            // acquire can only return if it can acquire all of the requested items 
-            let mut available = false;
-            while available == false {
-                // set available to true, so that it has an inital value
-                available = true;
-                // Check to see if all of the resources are available, if all are true then the and will
-                // result in a true, if atleast one is false then the and is false.
-                ($(available = available & can_acquire!($access($type)),)*);
-                // if not all types were available go to sleep on the Conditional Variable
-                if !available {
-                    mutex = first_type::get_mutex().1.wait(mutex).unwrap();
+            // StaticStorage is unused when the types are generic parameters bound by the trait
+            #[allow(unused_imports)]
+            use $crate::static_storage::StaticStorage;
+            let mut locks = $crate::storage_locks!($($access($type)),*);
+            // acquire can only return once it has acquired all of the requested items
+            match $crate::static_storage::acquire_locks(&mut locks) {
+                Ok(()) => Ok($crate::storage_guard!($($access($type)),*)),
+                Err(error) => Err(error),
+            }
+        }
+    };
+    // capture a World followed by an arbitrary number of arguments of the form Access(Type)
+    ($world:expr => $($access:tt($type:tt)),*) => {
+        {
+            let world : &$crate::world::World = &$world;
+            let mut locks = $crate::storage_locks!(world => $($access($type)),*);
+            match $crate::static_storage::acquire_locks(&mut locks) {
+                Ok(()) => Ok(unsafe { $crate::storage_guard!(world => $($access($type)),*) }),
+                Err(error) => Err(error),
+            }
+        }
+    };
+}
+
+/// The try_acquire! macro is the non blocking version of acquire!, it takes the same arguments but
+/// returns a Result<Guard, Error> immediately. If any of the storages aren't available then none of them
+/// are acquired and an Error::IllegalReadGuardAcquire or Error::IllegalWriteGuardAcquire is returned.
+/// 
+/// For example try_acquire!(Read(Type1), Write(Type2)) or try_acquire!(world => Read(Type1), Write(Type2))
+/// 
+/// This macro does NOT block
+#[macro_export] macro_rules! try_acquire {
+    ($($access:tt($type:tt)),*) => {
+        unsafe {
+            #[allow(unused_imports)]
+            use $crate::static_storage::StaticStorage;
+            let mut locks = $crate::storage_locks!($($access($type)),*);
+            match $crate::static_storage::try_acquire_locks(&mut locks) {
+                Ok(()) => Ok($crate::storage_guard!($($access($type)),*)),
+                Err(error) => Err(error),
+            }
+        }
+    };
+    ($world:expr => $($access:tt($type:tt)),*) => {
+        {
+            let world : &$crate::world::World = &$world;
+            let mut locks = $crate::storage_locks!(world => $($access($type)),*);
+            match $crate::static_storage::try_acquire_locks(&mut locks) {
+                Ok(()) => Ok(unsafe { $crate::storage_guard!(world => $($access($type)),*) }),
+                Err(error) => Err(error),
+            }
+        }
+    };
+}
+
+/// The acquire_timeout! macro is the same as acquire!, but gives up once the Duration passed as the first argument has
+/// passed, returning a Result<Guard, Error>. If it gives up none of the storages are acquired and an Error::IllegalReadGuardAcquire
+/// or Error::IllegalWriteGuardAcquire is returned for the storage that wasn't available.
+/// 
+/// For example acquire_timeout!(Duration::from_millis(5), Read(Type1), Write(Type2)) or
+/// acquire_timeout!(Duration::from_millis(5), world => Read(Type1), Write(Type2))
+/// 
+/// This macro CAN block for up to the Duration
+#[macro_export] macro_rules! acquire_timeout {
+    ($timeout:expr, $($access:tt($type:tt)),*) => {
+        {
+            let timeout : std::time::Duration = $timeout;
+            unsafe {
+                #[allow(unused_imports)]
+                use $crate::static_storage::StaticStorage;
+                let mut locks = $crate::storage_locks!($($access($type)),*);
+                match $crate::static_storage::acquire_locks_timeout(&mut locks, timeout) {
+                    Ok(()) => Ok($crate::storage_guard!($($access($type)),*)),
+                    Err(error) => Err(error),
                 }
             }
-            // Collect all the resources and store them in a tuple
-            let storages = ($(acquire_storage!($access($type)),)*);
-            // In order to maintain rust's idomatic vibe of no manual destruction for the user,
-            // we create a lambda destructor that will be called by the created guard for freeing
-            // all the aquired types
-            let destructor = || {
-                // acquire the mutex as we will be writing to the RW_Storages
-                let _mutex = first_type::get_mutex().0.lock().expect("Failed to access Component mutex");
-                // Undo the reads and the writes of the acquire
-                ($(release_storage!($access($type)),)*);
-                // wake up any threads that might be sleeping waiting for resources
-                first_type::get_mutex().1.notify_all();            
-            };
-            // create and return a Component Guard 
-            Guard::new(storages, destructor)
         }
-    }
-}
\ No newline at end of file
+    };
+    ($timeout:expr, $world:expr => $($access:tt($type:tt)),*) => {
+        {
+            let timeout : std::time::Duration = $timeout;
+            let world : &$crate::world::World = &$world;
+            let mut locks = $crate::storage_locks!(world => $($access($type)),*);
+            match $crate::static_storage::acquire_locks_timeout(&mut locks, timeout) {
+                Ok(()) => Ok(unsafe { $crate::storage_guard!(world => $($access($type)),*) }),
+                Err(error) => Err(error),
+            }
+        }
+    };
+}
+
+/// The reset! macro is used to reset an arbitrary number of StaticStorages back to their initial value,
+/// the Default of the ComponentStorage for components and the init expression of resources.
+/// Each storage is acquired for writing the same way as acquire!, so this macro CAN block.
+/// The storage is replaced without calling the on_remove hooks of the components in it, so any state kept
+/// up to date by the hooks has to be reset as well.
+///
+/// For example reset!(Type1, Type2) will clear the storages of Type1 and Type2, while
+/// reset!(world => Type1, Type2) will clear the storages of Type1 and Type2 in the World world.
+#[macro_export] macro_rules! reset {
+    ($($type:tt),*) => {
+        {
+            use $crate::static_storage::StaticStorage;
+            $($type::reset();)*
+        }
+    };
+    ($world:expr => $($type:tt),*) => {
+        {
+            let world : &$crate::world::World = &$world;
+            $(world.reset::<$type, _>();)*
+        }
+    };
+}
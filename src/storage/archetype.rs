@@ -0,0 +1,474 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::slice;
+use crate::entity::{EntityID, EntityRegister};
+use crate::resource::Resource;
+use crate::static_storage::{StaticStorage, StorageLock, Stored};
+
+/// Archetypes is an optional storage that keeps the components of each entity together with the components of every
+/// other entity that has exactly the same set of components, this set is called it's archetype. Each archetype stores
+/// one column, a Vec, per component, so querying (&mut Position, &Velocity, &Mass) is a linear scan through contiguous
+/// arrays instead of merging several storages. Adding or removing a component moves the entity to another archetype,
+/// which is slower than with a ComponentStorage, so this suits components that are iterated together every frame.
+///
+/// Archetypes coexists with ComponentStorages, a component is opted in simply by adding it to the Archetypes instead
+/// of giving it an impl_component!. Archetypes is a resource, systems use it with Read<Archetypes> and Write<Archetypes>,
+/// and despawning an entity removes it's components from the Archetypes.
+///
+/// For example archetypes.query::<(&mut Position, &Velocity)>() iterates (EntityID, (&mut Position, &Velocity))
+pub struct Archetypes {
+    archetypes: Vec<Archetype>,
+    /// The archetype with each sorted list of component types
+    index: HashMap<Vec<TypeId>, usize>,
+    /// The archetype and row of each entity
+    locations: HashMap<EntityID, (usize, usize)>,
+}
+
+/// An Archetype stores the entities that have the same set of components, the components of the entity at each row
+/// of entities are at the same row of each column
+pub struct Archetype {
+    /// The type of each column, sorted
+    types: Vec<TypeId>,
+    columns: Vec<Box<dyn Column>>,
+    entities: Vec<EntityID>,
+}
+
+/// A Column is a type erased Vec of components, it allows rows to be moved between archetypes without knowing their types
+trait Column : Send + Sync {
+    /// Creates an empty Vec of the same type
+    fn new_empty(&self) -> Box<dyn Column>;
+    /// Moves the component at the row to the end of the other Vec, which must be of the same type
+    fn move_row(&mut self, row : usize, other : &mut dyn Column);
+    /// Drops the component at the row
+    fn remove_row(&mut self, row : usize);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T : Send + Sync + 'static> Column for Vec<T> {
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(Vec::<T>::new())
+    }
+    fn move_row(&mut self, row : usize, other : &mut dyn Column) {
+        let other = other.as_any_mut().downcast_mut::<Vec<T>>().expect("Moved a row to a column of a different type");
+        other.push(self.swap_remove(row));
+    }
+    fn remove_row(&mut self, row : usize) {
+        self.swap_remove(row);
+    }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+impl Archetype {
+    /// Creates an empty archetype with the given columns
+    fn new(mut columns : Vec<(TypeId, Box<dyn Column>)>) -> Self {
+        columns.sort_by_key(|(type_id, _)| *type_id);
+        let (types, columns) = columns.into_iter().unzip();
+        Archetype { types, columns, entities: Vec::new() }
+    }
+
+    /// Returns the position of the column of T
+    fn column_index(&self, type_id : TypeId) -> Option<usize> {
+        self.types.binary_search(&type_id).ok()
+    }
+
+    /// Returns the column of T
+    fn column<T : 'static>(&self) -> Option<&Vec<T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        self.columns[index].as_any().downcast_ref::<Vec<T>>()
+    }
+
+    /// Returns the column of T mutably
+    fn column_mut<T : 'static>(&mut self) -> Option<&mut Vec<T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        self.columns[index].as_any_mut().downcast_mut::<Vec<T>>()
+    }
+
+    /// Returns the entities of the archetype
+    pub fn entities(&self) -> &[EntityID] {
+        &self.entities
+    }
+
+    /// Returns true if the archetype has a column for T
+    pub fn has<T : 'static>(&self) -> bool {
+        self.column_index(TypeId::of::<T>()).is_some()
+    }
+}
+
+impl Archetypes {
+    /// Creates an empty Archetypes
+    pub fn new() -> Self {
+        Archetypes { archetypes: Vec::new(), index: HashMap::new(), locations: HashMap::new() }
+    }
+
+    /// Returns the number of entities that have atleast one component
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Returns true if no entities have components
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Returns the archetypes that have been created
+    pub fn archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+
+    /// Returns true if the entity has a component in the Archetypes
+    pub fn contains(&self, id : EntityID) -> bool {
+        self.locations.contains_key(&id)
+    }
+
+    /// Adds the component to the entity, replacing the component if the entity already has one. If the entity
+    /// didn't have the component it's other components are moved to the archetype that includes it.
+    pub fn insert<T : Send + Sync + 'static>(&mut self, id : EntityID, value : T) {
+        let (source, row) = match self.locations.get(&id) {
+            Some(&location) => location,
+            None => {
+                let target = self.archetype_with(None, Some(Box::new(Vec::<T>::new())), TypeId::of::<T>());
+                let archetype = &mut self.archetypes[target];
+                archetype.column_mut::<T>().expect("The archetype has a column for T").push(value);
+                archetype.entities.push(id);
+                self.locations.insert(id, (target, archetype.entities.len() - 1));
+                return;
+            },
+        };
+        if let Some(column) = self.archetypes[source].column_mut::<T>() {
+            column[row] = value;
+            return;
+        }
+        let target = self.archetype_with(Some(source), Some(Box::new(Vec::<T>::new())), TypeId::of::<T>());
+        self.move_entity(id, source, row, target);
+        self.archetypes[target].column_mut::<T>().expect("The archetype has a column for T").push(value);
+    }
+
+    /// Removes the component from the entity and returns it, the entity's other components are moved to the
+    /// archetype without it.
+    pub fn remove<T : Send + Sync + 'static>(&mut self, id : EntityID) -> Option<T> {
+        let (source, row) = *self.locations.get(&id)?;
+        let value = self.archetypes[source].column_mut::<T>()?.swap_remove(row);
+        if self.archetypes[source].types.len() == 1 {
+            self.locations.remove(&id);
+            self.remove_entity(source, row);
+        } else {
+            let target = self.archetype_with(Some(source), None, TypeId::of::<T>());
+            self.move_entity(id, source, row, target);
+        }
+        Some(value)
+    }
+
+    /// Removes every component of the entity, returns false if it didn't have any
+    pub fn despawn(&mut self, id : EntityID) -> bool {
+        match self.locations.remove(&id) {
+            Some((archetype, row)) => {
+                for column in self.archetypes[archetype].columns.iter_mut() {
+                    column.remove_row(row);
+                }
+                self.remove_entity(archetype, row);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns a refrence to the component of the entity
+    pub fn get<T : 'static>(&self, id : EntityID) -> Option<&T> {
+        let &(archetype, row) = self.locations.get(&id)?;
+        self.archetypes[archetype].column::<T>()?.get(row)
+    }
+
+    /// Returns a mutable refrence to the component of the entity
+    pub fn get_mut<T : 'static>(&mut self, id : EntityID) -> Option<&mut T> {
+        let &(archetype, row) = self.locations.get(&id)?;
+        self.archetypes[archetype].column_mut::<T>()?.get_mut(row)
+    }
+
+    /// Iterates through every entity that has all of the components of the query, the query is a tuple of
+    /// &T and &mut T. Each archetype is scanned in turn, so the entities are not sorted by EntityID.
+    /// This function panics if the query uses the same component twice.
+    pub fn query<'a, Q : Query<'a>>(&'a mut self) -> QueryIter<'a, Q> {
+        let types = Q::types();
+        for (i, type_id) in types.iter().enumerate() {
+            assert!(!types[..i].contains(type_id), "A query can't use the same component more than once");
+        }
+        QueryIter::new(self.archetypes.as_mut_ptr(), self.archetypes.len())
+    }
+
+    /// The same as query but for queries that only read components, so only a refrence to the Archetypes is needed
+    pub fn iter<'a, Q : Query<'a> + ReadOnly>(&'a self) -> QueryIter<'a, Q> {
+        // ReadOnly queries never write through the pointer
+        QueryIter::new(self.archetypes.as_ptr() as *mut Archetype, self.archetypes.len())
+    }
+
+    /// Iterates through the columns of each archetype that has all of the components of the query, this
+    /// yields the entities of the archetype along with a slice of each component, such as (&mut [Position], &[Velocity])
+    pub fn chunks<'a, Q : Query<'a>>(&'a mut self) -> impl Iterator<Item=(&'a [EntityID], Q::Slices)> + 'a {
+        let types = Q::types();
+        for (i, type_id) in types.iter().enumerate() {
+            assert!(!types[..i].contains(type_id), "A query can't use the same component more than once");
+        }
+        let archetypes = self.archetypes.as_mut_ptr();
+        (0..self.archetypes.len()).filter_map(move |i| {
+            // Each archetype is visited once, and the query uses each of it's columns atmost once
+            let archetype = unsafe { archetypes.add(i) };
+            if !Q::matches(unsafe { &*archetype }) {
+                return None;
+            }
+            Some(unsafe { ((*archetype).entities(), Q::slices(archetype)) })
+        })
+    }
+
+    /// Returns the archetype with the components of the source archetype plus the added column, or without the
+    /// component of type_id if no column is added. The archetype is created if it doesn't exist yet.
+    fn archetype_with(&mut self, source : Option<usize>, added : Option<Box<dyn Column>>, type_id : TypeId) -> usize {
+        let source_types = source.map_or(&[][..], |source| &self.archetypes[source].types[..]);
+        let mut types : Vec<TypeId> = source_types.iter().copied().filter(|t| *t != type_id).collect();
+        if added.is_some() {
+            types.push(type_id);
+            types.sort();
+        }
+        if let Some(&index) = self.index.get(&types) {
+            return index;
+        }
+        let mut columns : Vec<(TypeId, Box<dyn Column>)> = match source {
+            Some(source) => {
+                let source = &self.archetypes[source];
+                source.types.iter().zip(source.columns.iter())
+                    .filter(|(t, _)| **t != type_id)
+                    .map(|(t, column)| (*t, column.new_empty()))
+                    .collect()
+            },
+            None => Vec::new(),
+        };
+        columns.extend(added.map(|column| (type_id, column)));
+        self.archetypes.push(Archetype::new(columns));
+        self.index.insert(types, self.archetypes.len() - 1);
+        self.archetypes.len() - 1
+    }
+
+    /// Moves the components of the entity from the row of the source archetype to the end of the target archetype,
+    /// the columns that the target doesn't have must already have been removed from the row
+    fn move_entity(&mut self, id : EntityID, source : usize, row : usize, target : usize) {
+        let (source_ref, target_ref) = if source < target {
+            let (first, second) = self.archetypes.split_at_mut(target);
+            (&mut first[source], &mut second[0])
+        } else {
+            let (first, second) = self.archetypes.split_at_mut(source);
+            (&mut second[0], &mut first[target])
+        };
+        for (type_id, column) in source_ref.types.iter().zip(source_ref.columns.iter_mut()) {
+            if let Some(index) = target_ref.column_index(*type_id) {
+                column.move_row(row, &mut *target_ref.columns[index]);
+            }
+        }
+        target_ref.entities.push(id);
+        self.locations.insert(id, (target, target_ref.entities.len() - 1));
+        self.remove_entity(source, row);
+    }
+
+    /// Removes the entity at the row from the entities of the archetype, the columns must already have had the row
+    /// removed. The last entity is moved into the row, so it's location is updated.
+    fn remove_entity(&mut self, archetype : usize, row : usize) {
+        let entities = &mut self.archetypes[archetype].entities;
+        entities.swap_remove(row);
+        if let Some(&moved) = entities.get(row) {
+            self.locations.insert(moved, (archetype, row));
+        }
+    }
+}
+
+impl Default for Archetypes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A Query describes the components that are fetched from each archetype, it is implemented for &T, &mut T and tuples of them
+pub trait Query<'a> {
+    /// The components of a single entity, such as (&'a mut Position, &'a Velocity)
+    type Item;
+    /// The columns of a single archetype, such as (&'a mut [Position], &'a [Velocity])
+    type Slices;
+    /// An iterator over the Items of Slices
+    type Iter : Iterator<Item=Self::Item>;
+    /// The components used by the query
+    fn types() -> Vec<TypeId>;
+    /// Returns true if the archetype has every component of the query
+    fn matches(archetype : &Archetype) -> bool;
+    /// Returns the columns of the archetype.
+    ///
+    /// # Safety
+    /// The archetype must match the query, it must be valid for 'a, and no other refrences to the columns can be used while
+    /// the slices are. If the query writes to a column the pointer must have been created from a mutable refrence.
+    unsafe fn slices(archetype : *mut Archetype) -> Self::Slices;
+    /// Iterates through the slices
+    fn iter(slices : Self::Slices) -> Self::Iter;
+}
+
+/// ReadOnly is implemented on the queries that don't write to any components, it is implemented on &T and on tuples of ReadOnly queries.
+///
+/// # Safety
+/// Archetypes::iter hands out the Items of a ReadOnly query from a shared refrence, so the Items of the query must not
+/// allow any component to be written. Implementing it on a query that yields mutable refrences, like &mut T, is undefined behaviour.
+pub unsafe trait ReadOnly {}
+
+impl<'a, T : 'static> Query<'a> for &'a T {
+    type Item = &'a T;
+    type Slices = &'a [T];
+    type Iter = slice::Iter<'a, T>;
+    fn types() -> Vec<TypeId> { vec![TypeId::of::<T>()] }
+    fn matches(archetype : &Archetype) -> bool { archetype.has::<T>() }
+    unsafe fn slices(archetype : *mut Archetype) -> &'a [T] {
+        (*archetype).column::<T>().expect("The archetype matches the query")
+    }
+    fn iter(slices : &'a [T]) -> Self::Iter { slices.iter() }
+}
+
+unsafe impl<T> ReadOnly for &T {}
+
+impl<'a, T : 'static> Query<'a> for &'a mut T {
+    type Item = &'a mut T;
+    type Slices = &'a mut [T];
+    type Iter = slice::IterMut<'a, T>;
+    fn types() -> Vec<TypeId> { vec![TypeId::of::<T>()] }
+    fn matches(archetype : &Archetype) -> bool { archetype.has::<T>() }
+    unsafe fn slices(archetype : *mut Archetype) -> &'a mut [T] {
+        // Only the column of T is borrowed, so other queries can borrow the other columns
+        let index = (*archetype).column_index(TypeId::of::<T>()).expect("The archetype matches the query");
+        let columns = &mut (*archetype).columns;
+        columns[index].as_any_mut().downcast_mut::<Vec<T>>().expect("The column has the type of it's TypeId")
+    }
+    fn iter(slices : &'a mut [T]) -> Self::Iter { slices.iter_mut() }
+}
+
+/// QueryIter iterates through the entities of every archetype that matches the query Q
+pub struct QueryIter<'a, Q : Query<'a>> {
+    archetypes: *mut Archetype,
+    len: usize,
+    next: usize,
+    current: Option<(slice::Iter<'a, EntityID>, Q::Iter)>,
+    _archetypes: PhantomData<&'a mut Archetype>,
+}
+
+impl<'a, Q : Query<'a>> QueryIter<'a, Q> {
+    /// Creates the iterator, the archetypes are only written to if the query writes
+    /// and those queries can only be created from a mutable refrence
+    fn new(archetypes : *mut Archetype, len : usize) -> Self {
+        QueryIter { archetypes, len, next: 0, current: None, _archetypes: PhantomData }
+    }
+}
+
+impl<'a, Q : Query<'a>> Iterator for QueryIter<'a, Q> {
+    type Item = (EntityID, Q::Item);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((entities, items)) = &mut self.current {
+                if let (Some(id), Some(item)) = (entities.next(), items.next()) {
+                    return Some((*id, item));
+                }
+            }
+            if self.next >= self.len {
+                return None;
+            }
+            // Each archetype is visited once, so the slices of different archetypes never alias
+            let archetype = unsafe { self.archetypes.add(self.next) };
+            self.next += 1;
+            if Q::matches(unsafe { &*archetype }) {
+                let entities = unsafe { (*archetype).entities.iter() };
+                self.current = Some((entities, Q::iter(unsafe { Q::slices(archetype) })));
+            }
+        }
+    }
+}
+
+/// TupleIter iterates through several iterators at once, it is the Iter of a tuple Query
+pub struct TupleIter<T>(T);
+
+/// impl_query_tuple! implements Query and ReadOnly on a tuple of Queries
+macro_rules! impl_query_tuple {
+    ($($t:ident $i:tt),*) => {
+        impl<'a, $($t : Query<'a>),*> Query<'a> for ($($t,)*) {
+            type Item = ($($t::Item,)*);
+            type Slices = ($($t::Slices,)*);
+            type Iter = TupleIter<($($t::Iter,)*)>;
+            fn types() -> Vec<TypeId> {
+                let mut types = Vec::new();
+                $(types.extend($t::types());)*
+                types
+            }
+            fn matches(archetype : &Archetype) -> bool {
+                $($t::matches(archetype))&&*
+            }
+            unsafe fn slices(archetype : *mut Archetype) -> Self::Slices {
+                ($($t::slices(archetype),)*)
+            }
+            fn iter(slices : Self::Slices) -> Self::Iter {
+                TupleIter(($($t::iter(slices.$i),)*))
+            }
+        }
+
+        impl<$($t : Iterator),*> Iterator for TupleIter<($($t,)*)> {
+            type Item = ($($t::Item,)*);
+            fn next(&mut self) -> Option<Self::Item> {
+                Some(($(self.0.$i.next()?,)*))
+            }
+        }
+
+        unsafe impl<$($t : ReadOnly),*> ReadOnly for ($($t,)*) {}
+    };
+}
+
+impl_query_tuple!(A 0);
+impl_query_tuple!(A 0, B 1);
+impl_query_tuple!(A 0, B 1, D 2);
+impl_query_tuple!(A 0, B 1, D 2, E 3);
+impl_query_tuple!(A 0, B 1, D 2, E 3, F 4);
+impl_query_tuple!(A 0, B 1, D 2, E 3, F 4, G 5);
+
+/// despawn_archetypes removes an entity from the static Archetypes, it is registered with the EntityRegister
+/// the first time the Archetypes are used
+fn despawn_archetypes(id : EntityID) {
+    let mut guard = acquire!(Write(Archetypes));
+    let (archetypes,) = guard.get();
+    archetypes.despawn(id);
+}
+
+impl StaticStorage<Archetypes> for Archetypes {
+    fn init() -> Archetypes {
+        Archetypes::new()
+    }
+    fn despawn(storage : &mut Archetypes, id : EntityID) {
+        storage.despawn(id);
+    }
+    unsafe fn get_static() -> &'static mut Archetypes {
+        use std::sync::Once;
+        // Initialize the static storage to a const (Option, Once)
+        static mut STATIC_STORAGE : (Option<Archetypes>, Once) = (None, Once::new());
+        // At runtime Change the static storages None to a Some(Archetypes)
+        (*std::ptr::addr_of!(STATIC_STORAGE.1)).call_once(|| {
+            STATIC_STORAGE.0 = Some(Archetypes::init());
+            // Register the storage so that despawned entities are removed from it
+            EntityRegister::register_storage(despawn_archetypes);
+        });
+        // Coerce a mutable refrence into a mutable pointer
+        let ptr = std::ptr::addr_of_mut!(STATIC_STORAGE.0);
+        match *ptr {
+            Some(ref mut x) => x,
+            None => unreachable!(),
+        }
+    }
+    fn get_lock() -> &'static StorageLock {
+        static LOCK : StorageLock = StorageLock::new();
+        &LOCK
+    }
+}
+
+impl Stored for Archetypes {
+    type Storage = Archetypes;
+}
+
+impl Resource for Archetypes {}
@@ -0,0 +1,86 @@
+/// BitSet is a growable set of indices stored as one bit per index, it is used by storages to track which
+/// indices are occupied. Iterating a BitSet yields the indices in ascending order and skips 64 empty indices at a time.
+#[derive(Clone, Default, Debug)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    /// Creates an empty BitSet
+    pub fn new() -> Self {
+        BitSet { words: Vec::new(), len: 0 }
+    }
+
+    /// Adds the index to the set, returns false if it was already in the set
+    pub fn insert(&mut self, index : usize) -> bool {
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let added = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        self.len += added as usize;
+        added
+    }
+
+    /// Removes the index from the set, returns false if it wasn't in the set
+    pub fn remove(&mut self, index : usize) -> bool {
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        match self.words.get_mut(word) {
+            Some(w) if *w & bit != 0 => {
+                *w &= !bit;
+                self.len -= 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns true if the index is in the set
+    pub fn contains(&self, index : usize) -> bool {
+        self.words.get(index / 64).is_some_and(|w| w & (1 << (index % 64)) != 0)
+    }
+
+    /// Returns the number of indices in the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no indices in the set
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every index from the set
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+    }
+
+    /// Iterates through the indices in the set in ascending order
+    pub fn iter(&self) -> BitIter<'_> {
+        BitIter { words: &self.words, word: 0, current: self.words.first().copied().unwrap_or(0) }
+    }
+}
+
+/// BitIter iterates through the indices of a BitSet in ascending order
+pub struct BitIter<'a> {
+    words: &'a [u64],
+    word: usize,
+    current: u64,
+}
+
+impl Iterator for BitIter<'_> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.word += 1;
+            self.current = *self.words.get(self.word)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        // clear the lowest set bit
+        self.current &= self.current - 1;
+        Some(self.word * 64 + bit)
+    }
+}
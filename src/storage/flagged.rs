@@ -0,0 +1,254 @@
+use std::collections::{BTreeSet, btree_set};
+use std::iter::Map;
+use std::marker::PhantomData;
+use crate::component::ComponentStorage;
+use crate::events::{Events, EventReader};
+use crate::join::{Join, JoinSource, StorageSource, Candidate, Probe};
+
+/// A ComponentEvent records a change to a FlaggedStorage
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ComponentEvent<K> {
+    Inserted(K),    // A component was added to an entity that didn't have one
+    Modified(K),    // A component was replaced or mutably accessed
+    Removed(K),     // A component was removed
+}
+
+/// A ChangeReader is the cursor of a reader of a FlaggedStorage, each system should keep it's own
+pub type ChangeReader<K> = EventReader<ComponentEvent<K>>;
+
+/// FlaggedStorage wraps another ComponentStorage and records which entities had a component inserted,
+/// modified or removed. Every call to component_get_mut that finds a component and every component passed over by
+/// component_iter_mut is recorded as modified, as the storage can't know if it was changed, so get_mut should be
+/// used when only some of the components are changed. A join passes over every component of a storage it iterates,
+/// even the ones it skips, so a FlaggedStorage should be joined mutably by wrapping it in Tracked, which only records
+/// the components the join yields. inner_mut can be used to change components without recording them.
+///
+/// The changes are read with a ChangeReader, each reader has it's own cursor so every system sees every change once.
+/// Like Events, the changes are dropped by update once every reader has read them or after two updates, so update
+/// should be called once a frame.
+///
+/// For example impl_component!(Position, FlaggedStorage<BTreeMap<EntityID, Position>, Position>)
+pub struct FlaggedStorage<S : ComponentStorage<C>, C> {
+    inner: S,
+    changes: Events<ComponentEvent<EntityKey<S, C>>>,
+    _component: PhantomData<fn() -> C>,
+}
+
+/// The EntityID of the inner storage of a FlaggedStorage
+type EntityKey<S, C> = <S as ComponentStorage<C>>::EntityID;
+
+impl<C, S : ComponentStorage<C>> FlaggedStorage<S, C> where S::EntityID : Ord + Copy {
+    /// Wraps a storage, the components already in the storage are not recorded as inserted
+    pub fn new(inner : S) -> Self {
+        FlaggedStorage { inner, changes: Events::new(), _component: PhantomData }
+    }
+
+    /// Creates a new reader, the reader will see every change made after it was created
+    pub fn reader(&self) -> ChangeReader<S::EntityID> {
+        self.changes.reader()
+    }
+
+    /// Returns the changes that the reader hasn't seen yet, and marks them as seen
+    pub fn changes(&self, reader : &mut ChangeReader<S::EntityID>) -> ChangeSet<S::EntityID> {
+        let mut set = ChangeSet { inserted: BTreeSet::new(), modified: BTreeSet::new(), removed: BTreeSet::new() };
+        for event in reader.read(&self.changes) {
+            set.record(*event);
+        }
+        set
+    }
+
+    /// Drops the changes that have been read by every reader, and the changes that were made before the last update
+    pub fn update(&mut self) {
+        self.changes.update();
+    }
+
+    /// Returns a refrence to the inner storage
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable refrence to the inner storage, changes made through it are not recorded
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<C, S : ComponentStorage<C>> Default for FlaggedStorage<S, C> where S::EntityID : Ord + Copy {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+impl<C, S : ComponentStorage<C>> ComponentStorage<C> for FlaggedStorage<S, C> where S::EntityID : Ord + Copy {
+    type EntityID = S::EntityID;
+    type Iter<'a> = S::Iter<'a> where Self : 'a, C : 'a;
+    type IterMut<'a> = FlaggedIterMut<'a, S::EntityID, C, S::IterMut<'a>> where Self : 'a, C : 'a;
+    fn component_insert(&mut self, key : Self::EntityID, value : C) {
+        let event = if self.inner.component_get(key).is_some() { ComponentEvent::Modified(key) } else { ComponentEvent::Inserted(key) };
+        self.changes.send(event);
+        self.inner.component_insert(key, value);
+    }
+    fn component_get(&self, key : Self::EntityID) -> Option<&C> {
+        self.inner.component_get(key)
+    }
+    fn component_get_mut(&mut self, key : Self::EntityID) -> Option<&mut C> {
+        let value = self.inner.component_get_mut(key);
+        if value.is_some() {
+            self.changes.send(ComponentEvent::Modified(key));
+        }
+        value
+    }
+    fn component_remove(&mut self, key : &Self::EntityID) {
+        if self.inner.component_get(*key).is_some() {
+            self.changes.send(ComponentEvent::Removed(*key));
+        }
+        self.inner.component_remove(key);
+    }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        self.inner.component_iter()
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        FlaggedIterMut { iter: self.inner.component_iter_mut(), changes: &mut self.changes }
+    }
+}
+
+/// FlaggedIterMut is the mutable iterator of a FlaggedStorage, it records every component it yields as modified
+pub struct FlaggedIterMut<'a, K, C : 'a, I : Iterator<Item=(K, &'a mut C)>> {
+    iter: I,
+    changes: &'a mut Events<ComponentEvent<K>>,
+}
+
+impl<'a, K : Copy, C : 'a, I : Iterator<Item=(K, &'a mut C)>> Iterator for FlaggedIterMut<'a, K, C, I> {
+    type Item = (K, &'a mut C);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        self.changes.send(ComponentEvent::Modified(key));
+        Some((key, value))
+    }
+}
+
+/// Tracked wraps a mutable refrence to a FlaggedStorage in a join, so that only the components the join yields are
+/// recorded as modified, instead of every component the join passes over.
+/// For example (Tracked(&mut **position), &**velocity).join()
+pub struct Tracked<'a, S : ComponentStorage<C>, C>(pub &'a mut FlaggedStorage<S, C>);
+
+/// TrackedSource is the JoinSource of Tracked, it records a component as modified when the join takes it
+pub struct TrackedSource<'a, K, C, I : Iterator<Item=(K, &'a mut C)>> {
+    inner: StorageSource<K, &'a mut C, I>,
+    changes: &'a mut Events<ComponentEvent<K>>,
+}
+
+impl<'a, K : Ord + Copy, C : 'a, I : Iterator<Item=(K, &'a mut C)>> JoinSource for TrackedSource<'a, K, C, I> {
+    type EntityID = K;
+    type Item = &'a mut C;
+    fn candidate(&mut self) -> Candidate<K> { self.inner.candidate() }
+    fn probe(&mut self, key : K) -> Probe<K> { self.inner.probe(key) }
+    fn skip(&mut self, key : K) { self.inner.skip(key) }
+    fn take(&mut self, key : K) -> &'a mut C {
+        self.changes.send(ComponentEvent::Modified(key));
+        self.inner.take(key)
+    }
+}
+
+impl<'a, C : 'a, S : ComponentStorage<C>> Join<C> for Tracked<'a, S, C> where S::EntityID : Ord + Copy {
+    type Source = TrackedSource<'a, S::EntityID, C, S::IterMut<'a>>;
+    fn into_source(self) -> Self::Source {
+        let FlaggedStorage { inner, changes, .. } = self.0;
+        TrackedSource { inner: StorageSource::new(inner.component_iter_mut()), changes }
+    }
+}
+
+/// A ChangeSet is the changes a reader hasn't seen yet, sorted by EntityID. An entity is only in one of the sets,
+/// an entity that was inserted and then modified is inserted, and an entity that was removed is only removed.
+pub struct ChangeSet<K> {
+    inserted: BTreeSet<K>,
+    modified: BTreeSet<K>,
+    removed: BTreeSet<K>,
+}
+
+impl<K : Ord + Copy> ChangeSet<K> {
+    /// Applies a change to the set
+    fn record(&mut self, event : ComponentEvent<K>) {
+        match event {
+            ComponentEvent::Inserted(key) => {
+                self.removed.remove(&key);
+                self.modified.remove(&key);
+                self.inserted.insert(key);
+            },
+            ComponentEvent::Modified(key) => if !self.inserted.contains(&key) {
+                self.modified.insert(key);
+            },
+            ComponentEvent::Removed(key) => {
+                self.inserted.remove(&key);
+                self.modified.remove(&key);
+                self.removed.insert(key);
+            },
+        }
+    }
+
+    /// The entities that were given the component
+    pub fn inserted(&self) -> btree_set::Iter<'_, K> {
+        self.inserted.iter()
+    }
+
+    /// The entities whose component was modified but not inserted
+    pub fn modified(&self) -> btree_set::Iter<'_, K> {
+        self.modified.iter()
+    }
+
+    /// The entities whose component was removed
+    pub fn removed(&self) -> btree_set::Iter<'_, K> {
+        self.removed.iter()
+    }
+
+    /// Returns true if there were no changes
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Changed is a join filter that only yields the entities that were inserted or modified in the ChangeSet, it yields ().
+/// For example (&**position, Changed(&changes)).join()
+pub struct Changed<'a, K>(pub &'a ChangeSet<K>);
+
+/// Added is a join filter that only yields the entities that were inserted in the ChangeSet, it yields ().
+/// For example (&**position, Added(&changes)).join()
+pub struct Added<'a, K>(pub &'a ChangeSet<K>);
+
+/// The sorted iterator of the EntityIDs of a join filter
+type FilterIter<'a, K> = Map<btree_set::Iter<'a, K>, fn(&'a K) -> (K, ())>;
+
+/// The JoinSource of Changed, the inserted and modified entities are merged so they stay sorted
+type ChangedIter<'a, K> = Map<MergeIter<btree_set::Iter<'a, K>>, fn(&'a K) -> (K, ())>;
+
+impl<'a, K : Ord + Copy> Join<Changed<'a, K>> for Changed<'a, K> {
+    type Source = StorageSource<K, (), ChangedIter<'a, K>>;
+    fn into_source(self) -> Self::Source {
+        let merged = MergeIter { first: self.0.inserted.iter().peekable(), second: self.0.modified.iter().peekable() };
+        StorageSource::new(merged.map((|k| (*k, ())) as fn(&'a K) -> (K, ())))
+    }
+}
+
+impl<'a, K : Ord + Copy> Join<Added<'a, K>> for Added<'a, K> {
+    type Source = StorageSource<K, (), FilterIter<'a, K>>;
+    fn into_source(self) -> Self::Source {
+        StorageSource::new(self.0.inserted.iter().map((|k| (*k, ())) as fn(&'a K) -> (K, ())))
+    }
+}
+
+/// MergeIter merges two sorted iterators of refrences into one sorted iterator
+pub struct MergeIter<I : Iterator> {
+    first: std::iter::Peekable<I>,
+    second: std::iter::Peekable<I>,
+}
+
+impl<'a, K : Ord + 'a, I : Iterator<Item=&'a K>> Iterator for MergeIter<I> {
+    type Item = &'a K;
+    fn next(&mut self) -> Option<&'a K> {
+        match (self.first.peek(), self.second.peek()) {
+            (Some(a), Some(b)) if b < a => self.second.next(),
+            (Some(_), _) => self.first.next(),
+            (None, _) => self.second.next(),
+        }
+    }
+}
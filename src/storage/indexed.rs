@@ -0,0 +1,145 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+use crate::component::ComponentStorage;
+
+/// IndexKey extracts the key that an IndexedStorage indexes a component by, it is usually implemented on an empty
+/// struct so that a component can be indexed in different ways.
+///
+/// For example impl IndexKey<Name> for ByName { type Key = String; fn key(name : &Name) -> String { name.0.clone() } }
+pub trait IndexKey<C> {
+    type Key : Ord + Clone;
+    fn key(component : &C) -> Self::Key;
+}
+
+/// IndexedStorage wraps another ComponentStorage and keeps a map from the key of each component to the entities that
+/// have a component with that key, so finding the entities with a Name or in a grid cell doesn't have to scan every
+/// component. The index is updated when a component is inserted or removed. A component that was accessed with
+/// component_get_mut or component_iter_mut might have changed it's key, so it is checked again by lookup and
+/// moved to it's new key the next time the storage is mutated.
+///
+/// For example impl_component!(Name, IndexedStorage<BTreeMap<EntityID, Name>, Name, ByName>)
+pub struct IndexedStorage<S : ComponentStorage<C>, C, I : IndexKey<C>> {
+    inner: S,
+    index: BTreeMap<I::Key, BTreeSet<S::EntityID>>,
+    /// The key that each entity is indexed by
+    keys: BTreeMap<S::EntityID, I::Key>,
+    /// The entities whose components were mutably accessed since the index was last updated
+    stale: BTreeSet<S::EntityID>,
+    /// true if every component was mutably accessed since the index was last updated
+    all_stale: bool,
+    _component: PhantomData<fn() -> C>,
+}
+
+impl<C, S : ComponentStorage<C>, I : IndexKey<C>> IndexedStorage<S, C, I> where S::EntityID : Ord + Copy {
+    /// Wraps a storage, indexing the components already in it
+    pub fn new(inner : S) -> Self {
+        let mut storage = IndexedStorage { inner, index: BTreeMap::new(), keys: BTreeMap::new(), stale: BTreeSet::new(), all_stale: true, _component: PhantomData };
+        storage.reindex();
+        storage
+    }
+
+    /// Returns the entities that have a component with the key, sorted by EntityID
+    pub fn lookup<Q : Ord + ?Sized>(&self, key : &Q) -> Vec<S::EntityID> where I::Key : Borrow<Q> {
+        if self.all_stale {
+            return self.inner.component_iter().filter(|(_, c)| I::key(c).borrow() == key).map(|(id, _)| id).collect();
+        }
+        let indexed = self.index.get(key).into_iter().flatten().filter(|id| !self.stale.contains(id));
+        let changed = self.stale.iter().filter(|id| self.inner.component_get(**id).is_some_and(|c| I::key(c).borrow() == key));
+        let found : BTreeSet<S::EntityID> = indexed.chain(changed).copied().collect();
+        found.into_iter().collect()
+    }
+
+    /// Returns the first entity that has a component with the key
+    pub fn lookup_first<Q : Ord + ?Sized>(&self, key : &Q) -> Option<S::EntityID> where I::Key : Borrow<Q> {
+        self.lookup(key).first().copied()
+    }
+
+    /// Returns true if an entity has a component with the key
+    pub fn contains_key<Q : Ord + ?Sized>(&self, key : &Q) -> bool where I::Key : Borrow<Q> {
+        !self.lookup(key).is_empty()
+    }
+
+    /// Returns a refrence to the inner storage
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Moves the components that were mutably accessed to their current keys
+    fn reindex(&mut self) {
+        if self.all_stale {
+            self.index.clear();
+            self.keys.clear();
+            let keys : Vec<(S::EntityID, I::Key)> = self.inner.component_iter().map(|(id, c)| (id, I::key(c))).collect();
+            for (id, key) in keys {
+                self.index_component(id, key);
+            }
+            self.all_stale = false;
+        }
+        for id in std::mem::take(&mut self.stale) {
+            self.unindex(id);
+            if let Some(component) = self.inner.component_get(id) {
+                self.index_component(id, I::key(component));
+            }
+        }
+    }
+
+    /// Adds the entity to the index under the key
+    fn index_component(&mut self, id : S::EntityID, key : I::Key) {
+        self.index.entry(key.clone()).or_default().insert(id);
+        self.keys.insert(id, key);
+    }
+
+    /// Removes the entity from the index
+    fn unindex(&mut self, id : S::EntityID) {
+        if let Some(key) = self.keys.remove(&id) {
+            if let Some(ids) = self.index.get_mut(&key) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.index.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl<C, S : ComponentStorage<C>, I : IndexKey<C>> Default for IndexedStorage<S, C, I> where S::EntityID : Ord + Copy {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+impl<C, S : ComponentStorage<C>, I : IndexKey<C>> ComponentStorage<C> for IndexedStorage<S, C, I> where S::EntityID : Ord + Copy {
+    type EntityID = S::EntityID;
+    type Iter<'a> = S::Iter<'a> where Self : 'a, C : 'a;
+    type IterMut<'a> = S::IterMut<'a> where Self : 'a, C : 'a;
+    fn component_insert(&mut self, key : Self::EntityID, value : C) {
+        self.reindex();
+        self.unindex(key);
+        self.index_component(key, I::key(&value));
+        self.inner.component_insert(key, value);
+    }
+    fn component_get(&self, key : Self::EntityID) -> Option<&C> {
+        self.inner.component_get(key)
+    }
+    fn component_get_mut(&mut self, key : Self::EntityID) -> Option<&mut C> {
+        self.reindex();
+        if self.inner.component_get(key).is_some() {
+            self.stale.insert(key);
+        }
+        self.inner.component_get_mut(key)
+    }
+    fn component_remove(&mut self, key : &Self::EntityID) {
+        self.reindex();
+        self.unindex(*key);
+        self.inner.component_remove(key);
+    }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        self.inner.component_iter()
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.reindex();
+        self.all_stale = true;
+        self.inner.component_iter_mut()
+    }
+}
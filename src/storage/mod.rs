@@ -0,0 +1,19 @@
+// Storages that implement ComponentStorage, any of them can be given to impl_component! instead of a BTreeMap
+mod bitset;
+mod flagged;
+mod vec;
+mod sparse_set;
+mod null;
+mod indexed;
+mod unique;
+// Archetypes is a separate storage that components can opt into instead of a ComponentStorage
+mod archetype;
+
+pub use self::bitset::{BitSet, BitIter};
+pub use self::flagged::{FlaggedStorage, FlaggedIterMut, ComponentEvent, ChangeReader, ChangeSet, Changed, Added, MergeIter, Tracked, TrackedSource};
+pub use self::vec::{VecStorage, VecIter, VecIterMut};
+pub use self::sparse_set::{SparseSet, SparseIter};
+pub use self::null::{NullStorage, TagStorage, NullIter};
+pub use self::indexed::{IndexedStorage, IndexKey};
+pub use self::unique::{UniqueStorage, UniqueError, UniquePolicy, Replace, Reject, Panic};
+pub use self::archetype::{Archetypes, Archetype, Query, ReadOnly, QueryIter, TupleIter};
@@ -0,0 +1,108 @@
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use crate::component::ComponentStorage;
+use crate::entity::EntityID;
+use super::bitset::{BitSet, BitIter};
+
+/// NullStorage is a ComponentStorage for tag components, zero sized types like Player or Dead that carry no data.
+/// It only stores which entities have the tag in a BitSet, along with their generations, so adding a tag doesn't
+/// allocate and joining with a tag is as fast as iterating the bits. Creating a NullStorage for a type that isn't
+/// zero sized fails to compile.
+///
+/// For example impl_component!(Dead, NullStorage<Dead>)
+pub struct NullStorage<T> {
+    tagged: BitSet,
+    generations: Vec<u32>,
+    _tag: PhantomData<T>,
+}
+
+/// TagStorage is another name for NullStorage
+pub type TagStorage<T> = NullStorage<T>;
+
+impl<T> NullStorage<T> {
+    /// Fails to compile when it is used for a T that isn't zero sized, tag relies on T being zero sized
+    const ZERO_SIZED : () = assert!(std::mem::size_of::<T>() == 0, "NullStorage can only store zero sized types");
+
+    /// Creates an empty NullStorage, this fails to compile if T isn't zero sized
+    pub fn new() -> Self {
+        let () = Self::ZERO_SIZED;
+        NullStorage { tagged: BitSet::new(), generations: Vec::new(), _tag: PhantomData }
+    }
+
+    /// Returns the number of entities with the tag
+    pub fn len(&self) -> usize {
+        self.tagged.len()
+    }
+
+    /// Returns true if no entities have the tag
+    pub fn is_empty(&self) -> bool {
+        self.tagged.is_empty()
+    }
+
+    /// Returns true if the entity has the tag
+    pub fn contains(&self, id : EntityID) -> bool {
+        self.tagged.contains(id.index()) && self.generations[id.index()] == id.generation()
+    }
+
+    /// Returns a refrence to the tag, T is zero sized so the refrence doesn't point to any memory
+    fn tag<'a>() -> &'a mut T {
+        // A dangling pointer is valid for zero sized types, which new checks T is when it is compiled
+        unsafe { &mut *NonNull::dangling().as_ptr() }
+    }
+}
+
+impl<T> Default for NullStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ComponentStorage<T> for NullStorage<T> {
+    type EntityID = EntityID;
+    type Iter<'a> = NullIter<'a, &'a T> where T : 'a;
+    type IterMut<'a> = NullIter<'a, &'a mut T> where T : 'a;
+    fn component_insert(&mut self, key : EntityID, _value : T) {
+        let index = key.index();
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+        // an id older than the tagged entity belongs to a despawned entity, so it doesn't untag the entity
+        if self.tagged.contains(index) && key.generation() < self.generations[index] {
+            return;
+        }
+        self.generations[index] = key.generation();
+        self.tagged.insert(index);
+    }
+    fn component_get(&self, key : EntityID) -> Option<&T> {
+        if self.contains(key) { Some(Self::tag()) } else { None }
+    }
+    fn component_get_mut(&mut self, key : EntityID) -> Option<&mut T> {
+        if self.contains(key) { Some(Self::tag()) } else { None }
+    }
+    fn component_remove(&mut self, key : &EntityID) {
+        if self.contains(*key) {
+            self.tagged.remove(key.index());
+        }
+    }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        NullIter { indices: self.tagged.iter(), generations: &self.generations, tag: || Self::tag() }
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        NullIter { indices: self.tagged.iter(), generations: &self.generations, tag: Self::tag }
+    }
+}
+
+/// NullIter iterates through the entities of a NullStorage sorted by EntityID, yielding a refrence to the tag for each
+pub struct NullIter<'a, R> {
+    indices: BitIter<'a>,
+    generations: &'a [u32],
+    tag: fn() -> R,
+}
+
+impl<'a, R> Iterator for NullIter<'a, R> {
+    type Item = (EntityID, R);
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.indices.next()?;
+        Some((EntityID::new(index as u32, self.generations[index]), (self.tag)()))
+    }
+}
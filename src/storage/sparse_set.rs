@@ -0,0 +1,174 @@
+use std::iter::Zip;
+use std::slice;
+use crate::component::ComponentStorage;
+use crate::entity::EntityID;
+
+/// The value of the sparse array for an index that has no component
+const EMPTY : u32 = u32::MAX;
+
+/// SparseSet is a ComponentStorage that packs the components into a dense Vec with no gaps. A sparse array maps the
+/// index of each EntityID to the position of it's component in the dense Vec, so inserting, removing and getting a
+/// component are all O(1), while the sparse array only costs 4 bytes per index. Removing a component moves the last
+/// component into it's position, so the dense Vec stays packed.
+///
+/// The dense components and their EntityIDs can be accessed directly as slices, which is the fastest way to process
+/// every component. Joins need the components sorted by EntityID, when the dense Vec is already sorted it is iterated
+/// directly, otherwise component_iter follows the sparse array and component_iter_mut sorts the dense Vec first.
+///
+/// For example impl_component!(Sprite, SparseSet<Sprite>)
+pub struct SparseSet<T> {
+    sparse: Vec<u32>,
+    entities: Vec<EntityID>,
+    dense: Vec<T>,
+    /// true if entities is sorted
+    sorted: bool,
+}
+
+impl<T> SparseSet<T> {
+    /// Creates an empty SparseSet
+    pub fn new() -> Self {
+        SparseSet { sparse: Vec::new(), entities: Vec::new(), dense: Vec::new(), sorted: true }
+    }
+
+    /// Returns the number of components in the storage
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Returns true if there are no components in the storage
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Returns the EntityIDs of the components, the EntityID at each position owns the component at the same position of as_slice
+    pub fn entities(&self) -> &[EntityID] {
+        &self.entities
+    }
+
+    /// Returns the packed components
+    pub fn as_slice(&self) -> &[T] {
+        &self.dense
+    }
+
+    /// Returns the packed components mutably
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.dense
+    }
+
+    /// Returns true if the components are packed in the order of their EntityIDs
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Removes the component of the entity by moving the last component into it's position, returning the component
+    pub fn swap_remove(&mut self, id : EntityID) -> Option<T> {
+        let position = self.position(id)?;
+        let last = self.dense.len() - 1;
+        if position != last {
+            self.sparse[self.entities[last].index()] = position as u32;
+            self.sorted = false;
+        }
+        self.sparse[id.index()] = EMPTY;
+        self.entities.swap_remove(position);
+        Some(self.dense.swap_remove(position))
+    }
+
+    /// Sorts the packed components by their EntityIDs
+    pub fn sort(&mut self) {
+        if self.sorted {
+            return;
+        }
+        let mut order : Vec<usize> = (0..self.dense.len()).collect();
+        order.sort_unstable_by_key(|&position| self.entities[position]);
+        let mut dense : Vec<Option<T>> = self.dense.drain(..).map(Some).collect();
+        self.dense = order.iter().map(|&position| dense[position].take().expect("Each position is moved once")).collect();
+        self.entities = order.iter().map(|&position| self.entities[position]).collect();
+        for (position, id) in self.entities.iter().enumerate() {
+            self.sparse[id.index()] = position as u32;
+        }
+        self.sorted = true;
+    }
+
+    /// Returns the position of the component of the entity in the dense Vec
+    fn position(&self, id : EntityID) -> Option<usize> {
+        match self.sparse.get(id.index()) {
+            Some(&position) if position != EMPTY && self.entities[position as usize] == id => Some(position as usize),
+            _ => None,
+        }
+    }
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ComponentStorage<T> for SparseSet<T> {
+    type EntityID = EntityID;
+    type Iter<'a> = SparseIter<'a, T> where T : 'a;
+    type IterMut<'a> = Zip<std::iter::Copied<slice::Iter<'a, EntityID>>, slice::IterMut<'a, T>> where T : 'a;
+    fn component_insert(&mut self, key : EntityID, value : T) {
+        let index = key.index();
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+        match self.sparse[index] {
+            EMPTY => {
+                self.sorted = self.sorted && self.entities.last().is_none_or(|last| *last < key);
+                self.sparse[index] = self.dense.len() as u32;
+                self.entities.push(key);
+                self.dense.push(value);
+            },
+            // the index is already in the set, either for this entity or a despawned one that had the same index.
+            // An id older than the one in the set belongs to a despawned entity, so it doesn't replace the component
+            position => {
+                if key.generation() < self.entities[position as usize].generation() {
+                    return;
+                }
+                self.entities[position as usize] = key;
+                self.dense[position as usize] = value;
+            },
+        }
+    }
+    fn component_get(&self, key : EntityID) -> Option<&T> {
+        self.position(key).map(|position| &self.dense[position])
+    }
+    fn component_get_mut(&mut self, key : EntityID) -> Option<&mut T> {
+        self.position(key).map(move |position| &mut self.dense[position])
+    }
+    fn component_remove(&mut self, key : &EntityID) {
+        self.swap_remove(*key);
+    }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        if self.sorted {
+            SparseIter::Dense(self.entities.iter().copied().zip(self.dense.iter()))
+        } else {
+            SparseIter::Sparse { sparse: self.sparse.iter(), entities: &self.entities, dense: &self.dense }
+        }
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.sort();
+        self.entities.iter().copied().zip(self.dense.iter_mut())
+    }
+}
+
+/// SparseIter iterates through the components of a SparseSet sorted by EntityID, it iterates the dense Vec
+/// directly when it is sorted and follows the sparse array otherwise
+pub enum SparseIter<'a, T> {
+    Dense(Zip<std::iter::Copied<slice::Iter<'a, EntityID>>, slice::Iter<'a, T>>),
+    Sparse { sparse: slice::Iter<'a, u32>, entities: &'a [EntityID], dense: &'a [T] },
+}
+
+impl<'a, T> Iterator for SparseIter<'a, T> {
+    type Item = (EntityID, &'a T);
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SparseIter::Dense(iter) => iter.next(),
+            SparseIter::Sparse { sparse, entities, dense } => {
+                let position = *sparse.find(|&&position| position != EMPTY)? as usize;
+                Some((entities[position], &dense[position]))
+            },
+        }
+    }
+}
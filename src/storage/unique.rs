@@ -0,0 +1,149 @@
+use std::fmt;
+use std::marker::PhantomData;
+use crate::component::ComponentStorage;
+use crate::entity::EntityID;
+
+/// UniqueStorage is a ComponentStorage for components that atmost one entity can have, like a MainCamera or an
+/// ActivePlayer. Inserting the component on a second entity is handled by the UniquePolicy P. Replace moves the
+/// component to the new entity, Reject keeps it on the entity that has it and records a UniqueError that can be
+/// read with take_rejected, and Panic panics. When the component is replaced the on_remove hooks are not called
+/// for the entity that lost it. The entity with the component can be found with get, or with single like any other join.
+///
+/// For example impl_component!(MainCamera, UniqueStorage<MainCamera>) or impl_component!(ActivePlayer, UniqueStorage<ActivePlayer, Reject>)
+pub struct UniqueStorage<T, P : UniquePolicy = Replace> {
+    value: Option<(EntityID, T)>,
+    /// The inserts that were rejected since take_rejected was last called
+    rejected: Vec<UniqueError>,
+    _policy: PhantomData<P>,
+}
+
+/// UniqueError is the error of inserting a unique component on an entity while another entity has it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UniqueError {
+    /// The name of the component
+    pub name: &'static str,
+    /// The entity that has the component
+    pub existing: EntityID,
+    /// The entity the component was inserted on
+    pub rejected: EntityID,
+}
+
+impl fmt::Display for UniqueError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is unique, it can't be added to {:?} while {:?} has it", self.name, self.rejected, self.existing)
+    }
+}
+
+impl std::error::Error for UniqueError {}
+
+/// UniquePolicy decides what happens when a UniqueStorage is given a component for a second entity
+pub trait UniquePolicy {
+    /// Called when the component is inserted on a second entity, the component
+    /// is moved to the second entity if this returns true
+    fn replace(error : &UniqueError) -> bool;
+}
+
+/// Replace moves the component of a UniqueStorage to the last entity it was inserted on
+pub struct Replace;
+
+impl UniquePolicy for Replace {
+    fn replace(_error : &UniqueError) -> bool { true }
+}
+
+/// Reject keeps the component of a UniqueStorage on the entity that has it, the rejected inserts are recorded
+pub struct Reject;
+
+impl UniquePolicy for Reject {
+    fn replace(_error : &UniqueError) -> bool { false }
+}
+
+/// Panic panics when the component of a UniqueStorage is inserted on a second entity
+pub struct Panic;
+
+impl UniquePolicy for Panic {
+    fn replace(error : &UniqueError) -> bool {
+        panic!("{}", error);
+    }
+}
+
+impl<T, P : UniquePolicy> UniqueStorage<T, P> {
+    /// Creates an empty UniqueStorage
+    pub fn new() -> Self {
+        UniqueStorage { value: None, rejected: Vec::new(), _policy: PhantomData }
+    }
+
+    /// Returns the entity that has the component
+    pub fn entity(&self) -> Option<EntityID> {
+        self.value.as_ref().map(|(id, _)| *id)
+    }
+
+    /// Returns the entity that has the component along with the component
+    pub fn get(&self) -> Option<(EntityID, &T)> {
+        self.value.as_ref().map(|(id, value)| (*id, value))
+    }
+
+    /// Returns the entity that has the component along with a mutable refrence to the component
+    pub fn get_mut(&mut self) -> Option<(EntityID, &mut T)> {
+        self.value.as_mut().map(|(id, value)| (*id, value))
+    }
+
+    /// Inserts the component on the entity unless another entity has it, no matter the UniquePolicy.
+    /// If another entity has it the component is given back along with the UniqueError
+    pub fn try_insert(&mut self, id : EntityID, value : T) -> Result<(), (UniqueError, T)> {
+        match self.entity() {
+            Some(existing) if existing != id => Err((UniqueError { name: std::any::type_name::<T>(), existing, rejected: id }, value)),
+            _ => {
+                self.value = Some((id, value));
+                Ok(())
+            },
+        }
+    }
+
+    /// Returns the inserts that were rejected by the Reject policy since this was last called
+    pub fn take_rejected(&mut self) -> Vec<UniqueError> {
+        std::mem::take(&mut self.rejected)
+    }
+
+    /// Removes the component and returns it along with the entity that had it
+    pub fn take(&mut self) -> Option<(EntityID, T)> {
+        self.value.take()
+    }
+}
+
+impl<T, P : UniquePolicy> Default for UniqueStorage<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, P : UniquePolicy> ComponentStorage<T> for UniqueStorage<T, P> {
+    type EntityID = EntityID;
+    type Iter<'a> = std::option::IntoIter<(EntityID, &'a T)> where Self : 'a, T : 'a;
+    type IterMut<'a> = std::option::IntoIter<(EntityID, &'a mut T)> where Self : 'a, T : 'a;
+    fn component_insert(&mut self, key : EntityID, value : T) {
+        if let Err((error, value)) = self.try_insert(key, value) {
+            if !P::replace(&error) {
+                self.rejected.push(error);
+                return;
+            }
+            self.value = Some((key, value));
+        }
+    }
+    fn component_get(&self, key : EntityID) -> Option<&T> {
+        self.get().filter(|(id, _)| *id == key).map(|(_, value)| value)
+    }
+    fn component_get_mut(&mut self, key : EntityID) -> Option<&mut T> {
+        self.get_mut().filter(|(id, _)| *id == key).map(|(_, value)| value)
+    }
+    fn component_remove(&mut self, key : &EntityID) {
+        if self.entity() == Some(*key) {
+            self.value = None;
+        }
+    }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        self.get().into_iter()
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.get_mut().into_iter()
+    }
+}
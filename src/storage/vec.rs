@@ -0,0 +1,129 @@
+use crate::component::ComponentStorage;
+use crate::entity::EntityID;
+use super::bitset::{BitSet, BitIter};
+
+/// VecStorage is a dense ComponentStorage that stores the component of each entity at the index of it's EntityID,
+/// so getting a component is a single index instead of a search. An occupancy BitSet tracks which indices have a
+/// component, which makes iterating skip large empty ranges quickly. The generation of each EntityID is stored as
+/// well, so an id that was despawned won't find the component of the entity that reused it's index, and inserting
+/// with it doesn't replace that component.
+///
+/// The storage is as long as the largest index that has had a component, so it is best for components that
+/// most entities have, such as a Position or Velocity.
+///
+/// For example impl_component!(Position, VecStorage<Position>)
+pub struct VecStorage<T> {
+    slots: Vec<Option<T>>,
+    generations: Vec<u32>,
+    occupied: BitSet,
+}
+
+impl<T> VecStorage<T> {
+    /// Creates an empty VecStorage
+    pub fn new() -> Self {
+        VecStorage { slots: Vec::new(), generations: Vec::new(), occupied: BitSet::new() }
+    }
+
+    /// Returns the number of components in the storage
+    pub fn len(&self) -> usize {
+        self.occupied.len()
+    }
+
+    /// Returns true if there are no components in the storage
+    pub fn is_empty(&self) -> bool {
+        self.occupied.is_empty()
+    }
+
+    /// Returns the index of the id if the storage has a component for it
+    fn index(&self, id : EntityID) -> Option<usize> {
+        let index = id.index();
+        if self.occupied.contains(index) && self.generations[index] == id.generation() { Some(index) } else { None }
+    }
+}
+
+impl<T> Default for VecStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ComponentStorage<T> for VecStorage<T> {
+    type EntityID = EntityID;
+    type Iter<'a> = VecIter<'a, T> where T : 'a;
+    type IterMut<'a> = VecIterMut<'a, T> where T : 'a;
+    fn component_insert(&mut self, key : EntityID, value : T) {
+        let index = key.index();
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+            self.generations.resize(index + 1, 0);
+        }
+        // a component of an older generation belonged to a despawned entity, so it is replaced, while an id older
+        // than the component belongs to a despawned entity, like one a deferred command held onto, so it is ignored
+        if self.occupied.contains(index) && key.generation() < self.generations[index] {
+            return;
+        }
+        self.slots[index] = Some(value);
+        self.generations[index] = key.generation();
+        self.occupied.insert(index);
+    }
+    fn component_get(&self, key : EntityID) -> Option<&T> {
+        self.index(key).and_then(|index| self.slots[index].as_ref())
+    }
+    fn component_get_mut(&mut self, key : EntityID) -> Option<&mut T> {
+        match self.index(key) {
+            Some(index) => self.slots[index].as_mut(),
+            None => None,
+        }
+    }
+    fn component_remove(&mut self, key : &EntityID) {
+        if let Some(index) = self.index(*key) {
+            self.slots[index] = None;
+            self.occupied.remove(index);
+        }
+    }
+    fn component_iter(&self) -> Self::Iter<'_> {
+        VecIter { slots: &self.slots, generations: &self.generations, indices: self.occupied.iter() }
+    }
+    fn component_iter_mut(&mut self) -> Self::IterMut<'_> {
+        VecIterMut { slots: &mut self.slots, generations: &self.generations, offset: 0, indices: self.occupied.iter() }
+    }
+}
+
+/// VecIter iterates through the components of a VecStorage sorted by EntityID
+pub struct VecIter<'a, T> {
+    slots: &'a [Option<T>],
+    generations: &'a [u32],
+    indices: BitIter<'a>,
+}
+
+impl<'a, T> Iterator for VecIter<'a, T> {
+    type Item = (EntityID, &'a T);
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.indices.next()?;
+        let value = self.slots[index].as_ref()?;
+        Some((EntityID::new(index as u32, self.generations[index]), value))
+    }
+}
+
+/// VecIterMut mutably iterates through the components of a VecStorage sorted by EntityID
+pub struct VecIterMut<'a, T> {
+    /// The slots after the last index that was yielded
+    slots: &'a mut [Option<T>],
+    generations: &'a [u32],
+    /// The index of the first slot in slots
+    offset: usize,
+    indices: BitIter<'a>,
+}
+
+impl<'a, T> Iterator for VecIterMut<'a, T> {
+    type Item = (EntityID, &'a mut T);
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.indices.next()?;
+        // split the slots so that the yielded refrence doesn't alias the remaining slots
+        let slots = std::mem::take(&mut self.slots);
+        let (slot, rest) = slots[index - self.offset..].split_first_mut()?;
+        self.slots = rest;
+        self.offset = index + 1;
+        Some((EntityID::new(index as u32, self.generations[index]), slot.as_mut()?))
+    }
+}
@@ -1,25 +1,335 @@
+use std::any::TypeId;
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+use crate::static_storage::{StaticStorage, Stored, StorageLock, Access, Guard, Error, acquire_locks};
+use crate::dispatcher::Dispatcher;
+use crate::resource::Resource;
+
 pub trait System {
-    /// The run function will be called and passed a mutable refrence to itself 
-    /// whenever the system is dispatched
-    fn run(&mut self);
+    /// The storages the system reads and writes, for example (Read<CompA>, Write<CompB>).
+    /// A system that doesn't use any storages can use ()
+    type SystemData : SystemData;
+
+    /// The run function will be called and passed a mutable refrence to itself
+    /// along with the storages of it's SystemData whenever the system is dispatched
+    fn run(&mut self, data : Data<'_, Self>);
+
+    /// dispatch acquires the storages of the SystemData the same way as acquire!, and then
+    /// runs the system with them. The storages are released once run returns.
+    fn dispatch(&mut self) where Self : Sized {
+        let mut locks = Self::SystemData::locks();
+        acquire_locks(&mut locks).expect("Failed to acquire the SystemData");
+        // The guard releases the storages even if the system panics
+        let _guard = Guard::new((), || unsafe { Self::SystemData::release() });
+        self.run(unsafe { Self::SystemData::fetch() });
+    }
+}
+
+/// Data is the refrences to the storages passed into the run function of the System S,
+/// for the SystemData (Read<CompA>, Write<CompB>) this is (&StorageA, &mut StorageB)
+pub type Data<'a, S> = <<S as System>::SystemData as SystemData>::Data<'a>;
+
+/// SystemData describes the storages used by a system, it is implemented on Read<T>, Write<T>
+/// and tuples of them. Dispatchers use accesses to know which storages a system uses without running it.
+pub trait SystemData {
+    /// The refrences to the storages that are passed into the system
+    type Data<'a>;
+    /// The TypeId of every StaticStorage that is used along with how it is accessed
+    fn accesses() -> Vec<(TypeId, Access)>;
+    /// The StorageLock of every StaticStorage that is used along with how it is accessed
+    fn locks() -> Vec<(&'static StorageLock, Access)>;
+    /// Retrieves the refrences to the storages.
+    ///
+    /// # Safety
+    /// The locks must already be acquired, and must be released with release once the refrences are no longer used.
+    unsafe fn fetch<'a>() -> Self::Data<'a>;
+    /// Releases the locks of the storages, returning the first Error that occured.
+    ///
+    /// # Safety
+    /// The refrences returned by fetch must no longer be used.
+    unsafe fn release() -> Result<(), Error>;
+}
+
+/// Read<T> is SystemData that gives the system a refrence to the storage of T
+pub struct Read<T>(PhantomData<T>);
+
+/// Write<T> is SystemData that gives the system a mutable refrence to the storage of T
+pub struct Write<T>(PhantomData<T>);
+
+impl<T : Stored + StaticStorage<T::Storage> + 'static> SystemData for Read<T> {
+    type Data<'a> = &'a T::Storage;
+    fn accesses() -> Vec<(TypeId, Access)> {
+        vec![(TypeId::of::<T>(), Access::Read)]
+    }
+    fn locks() -> Vec<(&'static StorageLock, Access)> {
+        vec![(T::get_lock(), Access::Read)]
+    }
+    unsafe fn fetch<'a>() -> Self::Data<'a> {
+        T::get_static()
+    }
+    unsafe fn release() -> Result<(), Error> {
+        T::release_read_guard()
+    }
+}
+
+impl<T : Stored + StaticStorage<T::Storage> + 'static> SystemData for Write<T> {
+    type Data<'a> = &'a mut T::Storage;
+    fn accesses() -> Vec<(TypeId, Access)> {
+        vec![(TypeId::of::<T>(), Access::Write)]
+    }
+    fn locks() -> Vec<(&'static StorageLock, Access)> {
+        vec![(T::get_lock(), Access::Write)]
+    }
+    unsafe fn fetch<'a>() -> Self::Data<'a> {
+        T::get_static()
+    }
+    unsafe fn release() -> Result<(), Error> {
+        T::release_write_guard()
+    }
 }
 
+/// impl_system_data_tuple! implements SystemData on a tuple of SystemData
+macro_rules! impl_system_data_tuple {
+    ($($t:ident),*) => {
+        impl<$($t : SystemData),*> SystemData for ($($t,)*) {
+            type Data<'a> = ($($t::Data<'a>,)*);
+            fn accesses() -> Vec<(TypeId, Access)> {
+                #[allow(unused_mut)]
+                let mut accesses = Vec::new();
+                $(accesses.extend($t::accesses());)*
+                accesses
+            }
+            fn locks() -> Vec<(&'static StorageLock, Access)> {
+                #[allow(unused_mut)]
+                let mut locks = Vec::new();
+                $(locks.extend($t::locks());)*
+                locks
+            }
+            #[allow(clippy::unused_unit)]
+            unsafe fn fetch<'a>() -> Self::Data<'a> {
+                ($($t::fetch(),)*)
+            }
+            unsafe fn release() -> Result<(), Error> {
+                // Every storage is released before returning the first error
+                #[allow(unused_mut)]
+                let mut result = Ok(());
+                $(result = result.and($t::release());)*
+                result
+            }
+        }
+    };
+}
+
+impl_system_data_tuple!();
+impl_system_data_tuple!(A);
+impl_system_data_tuple!(A, B);
+impl_system_data_tuple!(A, B, D);
+impl_system_data_tuple!(A, B, D, E);
+impl_system_data_tuple!(A, B, D, E, F);
+impl_system_data_tuple!(A, B, D, E, F, G);
+impl_system_data_tuple!(A, B, D, E, F, G, H);
+impl_system_data_tuple!(A, B, D, E, F, G, H, I);
+impl_system_data_tuple!(A, B, D, E, F, G, H, I, J);
+impl_system_data_tuple!(A, B, D, E, F, G, H, I, J, L);
+
 /// the dispatch! simply runs all the systems given sequentially
 #[macro_export] macro_rules! dispatch {
     ($($system:expr),*) => {
-        $($system.run();)*
+        {
+            #[allow(unused_imports)]
+            use $crate::system::System;
+            $($system.dispatch();)*
+        }
     };
 }
 
-/// the dispatch_parallel! macro spawns a thread for each system, then runs them
+/// the dispatch_parallel! macro runs each system as a task on the global ThreadPool, and returns once they have all finished.
+/// A ThreadPool can be passed first to run the systems on it instead, dispatch_parallel!(pool => &mut system_a, &mut system_b)
 #[macro_export] macro_rules! dispatch_parallel {
+    ($pool:expr => $($system:expr),*) => {
+        {
+            #[allow(unused_imports)]
+            use $crate::system::System;
+            let pool : &$crate::thread_pool::ThreadPool = &$pool;
+            pool.scope(|s| {
+                $(
+                    s.spawn(|| {
+                        $system.dispatch();
+                    });
+                )*
+            });
+        }
+    };
     ($($system:expr),*) => {
-        genecs::crossbeam_utils::thread::scope(|s| {
-            $(
-                s.spawn(|_| {
-                    $system.run();
-                });
-            )*
-        }).unwrap();
+        $crate::dispatch_parallel!($crate::thread_pool::ThreadPool::global() => $($system),*)
     };
-}
\ No newline at end of file
+}
+
+/// RunCriteria decides if a stage or a system is run, it is checked every time the stage or system could run
+pub type RunCriteria = Box<dyn FnMut() -> bool + Send>;
+
+/// should_run checks every RunCriteria, they are all checked even if one of them fails
+/// so that criteria like every_n_ticks count every check
+pub(crate) fn should_run(criteria : &mut [RunCriteria]) -> bool {
+    let mut run = true;
+    for criteria in criteria.iter_mut() {
+        run &= criteria();
+    }
+    run
+}
+
+/// every_n_ticks creates a RunCriteria that passes on the first check and then every n checks after that
+pub fn every_n_ticks(n : u64) -> impl FnMut() -> bool + Send + 'static {
+    let mut tick = 0;
+    move || {
+        let run = tick % n.max(1) == 0;
+        tick += 1;
+        run
+    }
+}
+
+/// resource_is creates a RunCriteria that reads the storage of R and passes if the function returns true,
+/// for example resource_is::<Paused, _>(|paused| !paused.0). The storage is acquired the same way as acquire!
+pub fn resource_is<R, F>(mut f : F) -> impl FnMut() -> bool + Send + 'static
+    where R : Stored + StaticStorage<R::Storage> + 'static, F : FnMut(&R::Storage) -> bool + Send + 'static {
+    move || {
+        acquire_locks(&mut [(R::get_lock(), Access::Read)]).expect("Failed to acquire the RunCriteria storage");
+        let _guard = Guard::new((), || unsafe { R::release_read_guard() });
+        f(unsafe { R::get_static() })
+    }
+}
+
+/// FixedTime is the resource used by the fixed stages of a Schedule. Every frame the time that has passed should
+/// be added to the accumulator, using accumulate, and each fixed stage will run it's systems once for every step
+/// in the accumulator. The steps are taken from the accumulator once per run of the Schedule, so every fixed stage
+/// runs the same number of times. To avoid falling further and further behind, atmost max_steps are taken a frame
+/// and the remaining steps are dropped.
+pub struct FixedTime {
+    /// The amount of time each run of a fixed stage simulates
+    pub step: Duration,
+    /// The time that hasn't been simulated yet
+    pub accumulator: Duration,
+    /// The maximum number of times a fixed stage is run in a single frame
+    pub max_steps: u32,
+}
+crate::impl_resource!(FixedTime, FixedTime { step: Duration::from_secs(1) / 60, accumulator: Duration::ZERO, max_steps: 8 });
+
+impl FixedTime {
+    /// Adds the time that has passed to the accumulator
+    pub fn accumulate(&mut self, delta : Duration) {
+        self.accumulator += delta;
+    }
+
+    /// Returns how far the accumulator is into the next step, between 0 and 1, which is usefull for interpolating when rendering
+    pub fn alpha(&self) -> f64 {
+        if self.step.is_zero() {
+            return 0.0;
+        }
+        self.accumulator.as_secs_f64() / self.step.as_secs_f64()
+    }
+
+    /// Removes the whole steps from the accumulator and returns how many there were, atmost max_steps are
+    /// returned and the whole steps beyond that are dropped
+    fn take_steps(&mut self) -> u32 {
+        if self.step.is_zero() {
+            return 0;
+        }
+        let steps = self.accumulator.as_nanos() / self.step.as_nanos();
+        self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+        steps.min(self.max_steps as u128) as u32
+    }
+}
+
+/// A Stage is a Dispatcher that is run by a Schedule, along with the criteria for running it
+struct Stage {
+    name: String,
+    dispatcher: Dispatcher,
+    criteria: Vec<RunCriteria>,
+    fixed: bool,
+}
+
+/// A Schedule runs a list of stages one after another. Each stage is a Dispatcher, so the systems of a stage
+/// are run in parallel when they don't conflict. A stage can be given RunCriteria with run_if, and it is skipped
+/// whenever one of them fails, individual systems can be given RunCriteria with DispatcherBuilder::run_if.
+///
+/// A fixed stage is run once for every step in the accumulator of the FixedTime resource, which may be zero or several
+/// times a frame, this keeps systems like physics running at the same rate no matter how long a frame takes. The steps
+/// are taken when the first fixed stage is run, and every fixed stage of the Schedule runs that many times.
+pub struct Schedule {
+    stages: Vec<Stage>,
+}
+
+impl Schedule {
+    /// Creates a Schedule with no stages
+    pub fn new() -> Self {
+        Schedule { stages: Vec::new() }
+    }
+
+    /// Adds a stage that is run once every time the Schedule is run
+    pub fn with_stage(mut self, name : &str, dispatcher : Dispatcher) -> Self {
+        self.stages.push(Stage { name: name.to_string(), dispatcher, criteria: Vec::new(), fixed: false });
+        self
+    }
+
+    /// Adds a stage that is run once for every step in the accumulator of FixedTime
+    pub fn with_fixed_stage(mut self, name : &str, dispatcher : Dispatcher) -> Self {
+        self.stages.push(Stage { name: name.to_string(), dispatcher, criteria: Vec::new(), fixed: true });
+        self
+    }
+
+    /// Adds a RunCriteria to the last stage that was added, the stage is only run when all of it's criteria pass.
+    /// The criteria of a fixed stage are checked once a frame, if they fail the stage skips that frame's steps.
+    /// The accumulator is left as is when every fixed stage was skipped.
+    pub fn run_if<C : FnMut() -> bool + Send + 'static>(mut self, criteria : C) -> Self {
+        match self.stages.last_mut() {
+            Some(stage) => stage.criteria.push(Box::new(criteria)),
+            None => panic!("run_if must be called after a stage is added"),
+        }
+        self
+    }
+
+    /// Runs every stage in order
+    pub fn run(&mut self) {
+        let mut steps = None;
+        for stage in self.stages.iter_mut() {
+            if !should_run(&mut stage.criteria) {
+                continue;
+            }
+            if !stage.fixed {
+                stage.dispatcher.dispatch();
+                continue;
+            }
+            // The steps are taken once, so that every fixed stage runs the same number of times,
+            // and FixedTime is released before the stage is dispatched as the systems may use it
+            let steps = *steps.get_or_insert_with(|| acquire!(Write(FixedTime)).get().0.take_steps());
+            for _ in 0..steps {
+                stage.dispatcher.dispatch();
+            }
+        }
+    }
+
+    /// Returns the names of the stages in the order they are run
+    pub fn stages(&self) -> Vec<&str> {
+        self.stages.iter().map(|stage| stage.name.as_str()).collect()
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Displays the stages of the Schedule along with the plan of each stage
+impl fmt::Display for Schedule {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        for stage in self.stages.iter() {
+            writeln!(f, "{}{}:", stage.name, if stage.fixed { " (fixed)" } else { "" })?;
+            for line in stage.dispatcher.to_string().lines() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, Condvar, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// A Task is a function that is run by one of the workers of a ThreadPool
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// The ThreadPool keeps a number of worker threads alive so that running systems in parallel
+/// doesn't need to spawn a thread for every system on every dispatch. Tasks are given to the
+/// pool with scope, which works like a scoped thread, so tasks can borrow from the stack.
+///
+/// dispatch_parallel! and the Dispatcher use the global ThreadPool by default, it's number of
+/// threads can be set with ThreadPool::init_global before it is first used.
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// The queue of tasks that is shared between the ThreadPool and it's workers
+struct Shared {
+    queue: Mutex<Queue>,
+    condvar: Condvar,
+}
+
+struct Queue {
+    tasks: VecDeque<Task>,
+    shutdown: bool,
+}
+
+/// The ThreadPool used by dispatch_parallel! and the Dispatcher
+static GLOBAL : OnceLock<ThreadPool> = OnceLock::new();
+
+impl Shared {
+    /// Removes the next task from the queue if there is one, this does not block
+    fn pop(&self) -> Option<Task> {
+        self.queue.lock().expect("Failed to access the ThreadPool queue").tasks.pop_front()
+    }
+}
+
+/// worker runs the tasks of the queue until the ThreadPool is dropped
+fn worker(shared : Arc<Shared>) {
+    loop {
+        let task = {
+            let mut queue = shared.queue.lock().expect("Failed to access the ThreadPool queue");
+            while queue.tasks.is_empty() && !queue.shutdown {
+                queue = shared.condvar.wait(queue).expect("Failed to access the ThreadPool queue");
+            }
+            match queue.tasks.pop_front() {
+                Some(task) => task,
+                None => return,
+            }
+        };
+        task();
+    }
+}
+
+impl ThreadPool {
+    /// Creates a ThreadPool with the given number of worker threads, atleast one thread is always created
+    pub fn new(threads : usize) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue { tasks: VecDeque::new(), shutdown: false }),
+            condvar: Condvar::new(),
+        });
+        let workers = (0..threads.max(1)).map(|i| {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name(format!("genecs-worker-{}", i))
+                .spawn(move || worker(shared))
+                .expect("Failed to spawn a ThreadPool worker")
+        }).collect();
+        ThreadPool { shared, workers }
+    }
+
+    /// Returns the number of worker threads of the pool
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns the global ThreadPool, creating it with one thread per cpu if it hasn't been created yet
+    pub fn global() -> &'static ThreadPool {
+        GLOBAL.get_or_init(|| ThreadPool::new(thread::available_parallelism().map_or(1, |n| n.get())))
+    }
+
+    /// Creates the global ThreadPool with the given number of threads. Returns false if the global
+    /// ThreadPool was already created, in which case it's number of threads is unchanged.
+    pub fn init_global(threads : usize) -> bool {
+        let mut created = false;
+        GLOBAL.get_or_init(|| {
+            created = true;
+            ThreadPool::new(threads)
+        });
+        created
+    }
+
+    /// Runs the function with a Scope that can spawn tasks on the pool. scope only returns once every
+    /// task that was spawned has finished, so the tasks can borrow anything that outlives the call to scope.
+    /// While waiting, the calling thread helps run the queued tasks.
+    /// If one of the tasks panicked, scope panics once every task has finished.
+    pub fn scope<'env, F, R>(&self, f : F) -> R where F : for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState { pending: Mutex::new(0), condvar: Condvar::new(), panicked: AtomicBool::new(false) }),
+            _env: PhantomData,
+        };
+        let result = {
+            // The tasks are waited on even if f panics, as they may borrow from the stack
+            let _wait = WaitOnDrop(&scope);
+            f(&scope)
+        };
+        if scope.state.panicked.load(Ordering::SeqCst) {
+            panic!("A task spawned on the ThreadPool panicked");
+        }
+        result
+    }
+
+    /// Adds a task to the queue and wakes up a worker to run it
+    fn push(&self, task : Task) {
+        self.shared.queue.lock().expect("Failed to access the ThreadPool queue").tasks.push_back(task);
+        self.shared.condvar.notify_one();
+    }
+}
+
+/// Stops the workers once they have finished the tasks in the queue
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.shutdown = true;
+        }
+        self.shared.condvar.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A Scope spawns tasks on a ThreadPool that can borrow anything that lives for 'env, it is created by ThreadPool::scope
+pub struct Scope<'scope, 'env : 'scope> {
+    pool: &'scope ThreadPool,
+    state: Arc<ScopeState>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+/// The number of tasks of a Scope that haven't finished, and whether any of them panicked
+struct ScopeState {
+    pending: Mutex<usize>,
+    condvar: Condvar,
+    panicked: AtomicBool,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Runs the function on one of the workers of the ThreadPool
+    pub fn spawn<F : FnOnce() + Send + 'env>(&self, f : F) {
+        *self.state.pending.lock().expect("Failed to access the Scope") += 1;
+        let state = self.state.clone();
+        let task : Box<dyn FnOnce() + Send + 'env> = Box::new(move || {
+            // Catch panics so that the worker survives and the scope can report them
+            if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+                state.panicked.store(true, Ordering::SeqCst);
+            }
+            let mut pending = state.pending.lock().unwrap_or_else(|e| e.into_inner());
+            *pending -= 1;
+            if *pending == 0 {
+                state.condvar.notify_all();
+            }
+        });
+        // The scope waits for every task to finish before 'env ends, so the task can be treated as 'static
+        let task : Task = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'env>, Task>(task) };
+        self.pool.push(task);
+    }
+
+    /// Blocks until every task of the scope has finished, running queued tasks while there are any
+    fn wait(&self) {
+        loop {
+            if *self.state.pending.lock().unwrap_or_else(|e| e.into_inner()) == 0 {
+                return;
+            }
+            // Helping with the queue keeps a scope inside of a task from starving the pool
+            if let Some(task) = self.pool.shared.pop() {
+                task();
+                continue;
+            }
+            let pending = self.state.pending.lock().unwrap_or_else(|e| e.into_inner());
+            if *pending > 0 {
+                drop(self.state.condvar.wait(pending).unwrap_or_else(|e| e.into_inner()));
+            }
+        }
+    }
+}
+
+/// Waits for the tasks of a Scope when dropped
+struct WaitOnDrop<'a, 'scope, 'env>(&'a Scope<'scope, 'env>);
+
+impl Drop for WaitOnDrop<'_, '_, '_> {
+    fn drop(&mut self) {
+        self.0.wait();
+    }
+}
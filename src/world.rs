@@ -0,0 +1,200 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+use crate::commands::Commands;
+use crate::static_storage::{StaticStorage, StorageLock, Access, Error, acquire_locks};
+use crate::entity::{EntityID, EntityAllocator};
+
+/// A World owns it's own instance of every component storage and resource along with it's own
+/// EntityAllocator, this allows for multiple independent ECS instances to exist side by side.
+/// The statics created by impl_component! and impl_resource! act as the default world.
+///
+/// A World uses the StaticStorage implementation of a type to find it's Storage and initial value,
+/// so any type passed to impl_component! or impl_resource! can be stored in a World. Storages are
+/// created the first time they are acquired.
+///
+/// The storages of a world are acquired with the acquire! macro by passing the world before the storages
+/// acquire!(world => Read(Type1), Write(Type2)) will return a Guard with refrences to the storages of the world.
+pub struct World {
+    slots: Mutex<HashMap<TypeId, Box<Slot>>>,
+    entities: EntityAllocator,
+}
+
+/// A Slot contains a storage of the World along with the StorageLock that keeps track of the readers
+/// and writers of it, and a function that removes an entity from the storage. The mutex of the World is
+/// only held while looking up a Slot, each Slot is boxed so that refrences to it stay valid after the
+/// mutex is released.
+struct Slot {
+    storage: NonNull<dyn Any + Send + Sync>,
+    lock: StorageLock,
+    despawn: fn(&mut (dyn Any + Send + Sync), EntityID),
+}
+
+/// The storage of a Slot is Send and Sync, and the StorageLock guards access to it
+unsafe impl Send for Slot {}
+unsafe impl Sync for Slot {}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        // The storage was created with Box::new in Slot::new
+        unsafe { drop(Box::from_raw(self.storage.as_ptr())) }
+    }
+}
+
+/// init_slot creates the initial value of the Storage S of T for a World. The Commands resource targets the static
+/// storages, so the Commands of a World are detached so that they aren't recorded into by mistake
+fn init_slot<T : StaticStorage<S>, S : 'static>() -> S {
+    let mut storage = T::init();
+    if let Some(commands) = (&mut storage as &mut dyn Any).downcast_mut::<Commands>() {
+        commands.detach();
+    }
+    storage
+}
+
+/// despawn_slot removes an entity from the type erased storage of T
+fn despawn_slot<T : StaticStorage<S>, S : 'static>(storage : &mut (dyn Any + Send + Sync), id : EntityID) {
+    if let Some(storage) = storage.downcast_mut::<S>() {
+        T::despawn(storage, id);
+    }
+}
+
+impl Slot {
+    /// Creates a new Slot for the Storage S of T
+    fn new<T : StaticStorage<S>, S : Send + Sync + 'static>(storage : S) -> Self {
+        let storage : Box<dyn Any + Send + Sync> = Box::new(storage);
+        Slot {
+            storage: unsafe { NonNull::new_unchecked(Box::into_raw(storage)) },
+            lock: StorageLock::new(),
+            despawn: despawn_slot::<T, S>,
+        }
+    }
+}
+
+impl World {
+    /// Creates a new World with no storages or entities
+    pub fn new() -> Self {
+        World {
+            slots: Mutex::new(HashMap::new()),
+            entities: EntityAllocator::new(),
+        }
+    }
+
+    /// Inserts the Storage of T into the world, replacing the current one. This can be used
+    /// to give a resource a different value than it's initial value in this World.
+    pub fn insert<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&mut self, storage : S) {
+        let slots = self.slots.get_mut().expect("Failed to access World mutex");
+        slots.insert(TypeId::of::<T>(), Box::new(Slot::new::<T, S>(storage)));
+    }
+
+    /// Replaces the Storage of T with it's initial value, the storage is acquired for writing
+    /// so this function blocks until there are no other readers or writers of it.
+    pub fn reset<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) {
+        let mut guard = crate::acquire!(self => Write(T));
+        let (storage,) = guard.get();
+        **storage = init_slot::<T, S>();
+    }
+
+    /// Returns a new EntityID that is unique to this World
+    pub fn create_entity(&self) -> EntityID {
+        self.entities.allocate()
+    }
+
+    /// Returns true if the EntityID was created by this World and hasn't been despawned
+    pub fn is_alive(&self, id : EntityID) -> bool {
+        self.entities.is_alive(id)
+    }
+
+    /// Get the EntityAllocator of this World
+    pub fn entities(&self) -> &EntityAllocator {
+        &self.entities
+    }
+
+    /// Removes the entity from every storage of the world and then frees it's id.
+    /// This acquires each storage for writing one at a time, so this will deadlock if
+    /// called while holding a guard to one of the World's storages.
+    /// Returns false if the id was already freed.
+    pub fn despawn(&self, id : EntityID) -> bool {
+        if !self.entities.is_alive(id) {
+            return false;
+        }
+        let slots : Vec<&Slot> = {
+            let slots = self.slots.lock().expect("Failed to access World mutex");
+            // Slots are boxed and only removed by insert, which requires a mutable refrence to the World
+            slots.values().map(|slot| unsafe { &*(&**slot as *const Slot) }).collect()
+        };
+        for slot in slots {
+            acquire_locks(&mut [(&slot.lock, Access::Write)]).expect("Failed to acquire a World storage");
+            // The storage has been acquired for writing, so it can be modified
+            unsafe { (slot.despawn)(&mut *slot.storage.as_ptr(), id) };
+            slot.lock.release_write().expect("Failed to release a World storage");
+        }
+        self.entities.free(id)
+    }
+
+    /// Retrieves the slot of T, initializing it if this is the first time it was used
+    fn slot<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> &Slot {
+        let mut slots = self.slots.lock().expect("Failed to access World mutex");
+        let slot = slots.entry(TypeId::of::<T>()).or_insert_with(|| Box::new(Slot::new::<T, S>(init_slot::<T, S>())));
+        // Slots are boxed and only removed by insert, which requires a mutable refrence to the World
+        unsafe { &*(&**slot as *const Slot) }
+    }
+
+    /// get_lock returns the StorageLock of the storage of T, it is used by the acquire! macro
+    pub fn get_lock<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> &StorageLock {
+        &self.slot::<T, S>().lock
+    }
+
+    /// get_storage returns a mutable refrence to the storage of T, it is used by the acquire! macro.
+    ///
+    /// # Safety
+    /// The refrence must only be used while the reader or writer is accounted for in the StorageLock from get_lock.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn get_storage<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> &mut S {
+        // The storage is boxed so it won't move, and isn't removed while the World is borrowed
+        (*self.slot::<T, S>().storage.as_ptr()).downcast_mut::<S>().expect("World storage had the wrong type")
+    }
+
+    /// read increments the number of readers of the storage of T and returns a refrence to it.
+    /// An Error::IllegalReadGuardAcquire is returned if there is currently a writer in the storage.
+    /// This function does not block.
+    ///
+    /// # Safety
+    /// The reader must be released with release_read once the refrence is no longer used.
+    pub unsafe fn read<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> Result<&S, Error> {
+        self.get_lock::<T, S>().try_read()?;
+        Ok(self.get_storage::<T, S>())
+    }
+
+    /// write sets the writer of the storage of T and returns a mutable refrence to it.
+    /// An Error::IllegalWriteGuardAcquire is returned if there is currently a writer or reader in the storage.
+    /// This function does not block.
+    ///
+    /// # Safety
+    /// The writer must be released with release_write once the refrence is no longer used.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn write<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> Result<&mut S, Error> {
+        self.get_lock::<T, S>().try_write()?;
+        Ok(self.get_storage::<T, S>())
+    }
+
+    /// release_read decrements the number of readers of the storage of T, it is used by the acquire! macro.
+    /// An Error::AliasingDetected is returned if there is a writer.
+    /// An Error::UnacountedGuard is returned if the storage didn't have readers to free.
+    pub fn release_read<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> Result<(), Error> {
+        self.get_lock::<T, S>().release_read()
+    }
+
+    /// release_write removes the writer of the storage of T, it is used by the acquire! macro.
+    /// An Error::AliasingDetected is returned if there are readers.
+    /// An Error::UnacountedGuard is returned if the storage didn't have a writer to free.
+    pub fn release_write<T : StaticStorage<S> + 'static, S : Send + Sync + 'static>(&self) -> Result<(), Error> {
+        self.get_lock::<T, S>().release_write()
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,118 @@
+/// Test that commands recorded by parallel systems are applied at the end of the Dispatcher stage,
+/// and that the Dispatcher leaves the commands recorded outside of it alone
+#[test]
+fn commands_test(){
+    use genecs::component::Component;
+    use genecs::entity::{Entity, EntityID};
+    use genecs::commands::Commands;
+    use genecs::dispatcher::Dispatcher;
+    use genecs::system::{System, Read, Data};
+    use std::collections::BTreeMap;
+
+    struct Health(u32);
+    impl_component!(Health, BTreeMap<EntityID, Health>);
+
+    struct Dead();
+    impl_component!(Dead, BTreeMap<EntityID, Dead>);
+
+    struct Spawned();
+    impl_component!(Spawned, BTreeMap<EntityID, Spawned>);
+
+    /// Marks the entities without health as dead, only reading Health
+    struct MarkDead();
+    impl System for MarkDead {
+        type SystemData = (Read<Health>, Read<Commands>);
+        fn run(&mut self, (health, commands) : Data<Self>) {
+            for (id, health) in health.iter() {
+                if health.0 == 0 {
+                    commands.add(*id, Dead());
+                    commands.remove::<Health, _>(*id);
+                }
+            }
+        }
+    }
+
+    /// Spawns an entity, runs in parallel with MarkDead
+    struct Spawn();
+    impl System for Spawn {
+        type SystemData = Read<Commands>;
+        fn run(&mut self, commands : Data<Self>) {
+            let id = commands.spawn();
+            commands.add(id, Spawned());
+        }
+    }
+
+    /// Despawns the dead entities, which were marked in the stage before
+    struct Despawn();
+    impl System for Despawn {
+        type SystemData = (Read<Dead>, Read<Commands>);
+        fn run(&mut self, (dead, commands) : Data<Self>) {
+            for id in dead.keys() {
+                commands.despawn(*id);
+            }
+        }
+    }
+
+    let alive = {
+        let mut guard = acquire!(Write(Health));
+        let (health,) = guard.get();
+        Entity::new().add(*health, Health(0));
+        Entity::new().add(*health, Health(10)).get_id()
+    };
+
+    let mut dispatcher = Dispatcher::builder()
+        .with(MarkDead(), "mark_dead")
+        .with(Spawn(), "spawn")
+        .with(Despawn(), "despawn").after("mark_dead")
+        .build();
+    assert!(dispatcher.stages() == vec![vec!["mark_dead", "spawn"], vec!["despawn"]]);
+    acquire!(Read(Commands)).get().0.add(alive, Dead());
+    dispatcher.dispatch();
+
+    let mut guard = acquire!(Read(Health), Read(Dead), Read(Spawned), Read(Commands));
+    let (health, dead, spawned, commands) = guard.get();
+    assert!(commands.len() == 1);
+    assert!(health.keys().copied().collect::<Vec<_>>() == vec![alive]);
+    assert!(dead.is_empty());
+    assert!(spawned.len() == 1);
+    drop(guard);
+    Commands::apply_queued();
+    assert!(acquire!(Read(Dead)).get().0.keys().copied().collect::<Vec<_>>() == vec![alive]);
+}
+
+/// Test that the Commands of a World spawn and change entities in the World, and that
+/// the Commands resource of a World can't be recorded into
+#[test]
+fn world_commands_test(){
+    use genecs::component::Component;
+    use genecs::entity::EntityID;
+    use genecs::commands::Commands;
+    use genecs::world::World;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    struct Name(&'static str);
+    impl_component!(Name, BTreeMap<EntityID, Name>);
+
+    let world = Arc::new(World::new());
+    let commands = Commands::with_world(world.clone());
+    let first = commands.spawn();
+    let second = commands.spawn();
+    assert!(world.is_alive(first) && world.is_alive(second));
+    commands.add(first, Name("first"));
+    commands.add(second, Name("second"));
+    commands.apply();
+    assert!(acquire!(world => Read(Name)).get().0.get(&second).is_some_and(|name| name.0 == "second"));
+    commands.remove::<Name, _>(first);
+    commands.despawn(second);
+    commands.apply();
+    assert!(world.is_alive(first) && !world.is_alive(second));
+    assert!(acquire!(world => Read(Name)).get().0.is_empty());
+    assert!(acquire!(Read(Name)).get().0.is_empty());
+
+    let mut guard = acquire!(world => Read(Commands));
+    let (detached,) = guard.get();
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { detached.spawn(); })).is_err());
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { detached.despawn(first); })).is_err());
+    assert!(detached.is_empty());
+}
@@ -2,7 +2,6 @@
 /// and joining
 #[test]
 fn component_test(){
-    use kv_join;
     use genecs::component::{Component, ComponentStorage};
     use std::collections::BTreeMap;
 
@@ -36,14 +35,14 @@ fn component_test(){
     st3.component_insert(10, SimpleTuple3(5));
     st3.component_insert(12, SimpleTuple3(6));
     
-    let mut iter = kvand_join!(st.iter(), st2.iter(), st3.iter());
+    let mut iter = components.join();
     let (k, (v1,v2,v3)) = iter.next().unwrap();
-    assert!(*k == 6, "key of first join was incorrect");
+    assert!(k == 6, "key of first join was incorrect");
     assert!(v1.0 == 2, "First value of first join was incorrect");
     assert!(v2.0 == 2, "Second value of first join was incorrect");
     assert!(v3.0 == 3, "Third value of first join was incorrect");
     let (k, (v1,v2,v3)) = iter.next().unwrap();
-    assert!(*k == 12, "key of second join was incorrect");
+    assert!(k == 12, "key of second join was incorrect");
     assert!(v1.0 == 4, "First value of second join was incorrect");
     assert!(v2.0 == 3, "Second value of second join was incorrect");
     assert!(v3.0 == 6, "Third value of second join was incorrect");
@@ -90,6 +89,7 @@ fn multiple_reads(){
     use std::collections::BTreeMap;
 
     impl_component!(SimpleTuple, BTreeMap<u64, SimpleTuple>);
+    #[allow(dead_code)]
     struct SimpleTuple(u64);
     let _components = acquire!(Read(SimpleTuple), Read(SimpleTuple), Read(SimpleTuple));
 } 
@@ -106,7 +106,7 @@ fn multiple_writes(){
     let mut components = acquire!(Write(SimpleTuple));
     let (st,) = components.get();
     let now = Instant::now();
-    let now2 = now.clone();
+    let now2 = now;
     let child = thread::spawn(move || {
         assert!(now2.elapsed().as_millis() < 100);
         let mut components = acquire!(Write(SimpleTuple));
@@ -136,4 +136,164 @@ fn multiple_writes(){
     let (k,v) = iter.next().unwrap();
     assert!(*k == 3 && v.0 == 3);
     assert!(iter.next().is_none());
-}
\ No newline at end of file
+}
+
+/// Test that reset! replaces component storages with empty storages
+#[test]
+fn reset_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use std::collections::BTreeMap;
+
+    impl_component!(SimpleTuple, BTreeMap<u64, SimpleTuple>);
+    struct SimpleTuple(u64);
+
+    impl_component!(SimpleTuple2, BTreeMap<u64, SimpleTuple2>);
+    struct SimpleTuple2(u64);
+    {
+        let mut components = acquire!(Write(SimpleTuple), Write(SimpleTuple2));
+        let (st, st2) = components.get();
+        st.component_insert(1, SimpleTuple(1));
+        st2.component_insert(2, SimpleTuple2(2));
+        assert!(st.component_get(1).unwrap().0 == 1 && st2.component_get(2).unwrap().0 == 2);
+    }
+    reset!(SimpleTuple, SimpleTuple2);
+    let mut components = acquire!(Read(SimpleTuple), Read(SimpleTuple2));
+    let (st, st2) = components.get();
+    assert!(st.is_empty() && st2.is_empty());
+}
+
+/// Test that threads acquiring overlapping storages in different orders don't deadlock or lose writes
+#[test]
+fn overlapping_writes(){
+    use genecs::component::{Component, ComponentStorage};
+    use std::collections::BTreeMap;
+    use std::thread;
+
+    impl_component!(First, BTreeMap<u64, First>);
+    struct First(u64);
+
+    impl_component!(Second, BTreeMap<u64, Second>);
+    struct Second(u64);
+
+    {
+        let mut components = acquire!(Write(First), Write(Second));
+        let (first, second) = components.get();
+        first.component_insert(0, First(0));
+        second.component_insert(0, Second(0));
+    }
+    thread::scope(|s| {
+        for i in 0..8 {
+            s.spawn(move || {
+                for _ in 0..1000 {
+                    if i % 2 == 0 {
+                        let mut components = acquire!(Write(First), Write(Second));
+                        let (first, second) = components.get();
+                        first.component_get_mut(0).unwrap().0 += 1;
+                        second.component_get_mut(0).unwrap().0 += 1;
+                    } else {
+                        let mut components = acquire!(Write(Second), Read(First));
+                        let (second, first) = components.get();
+                        second.component_get_mut(0).unwrap().0 += first.component_get(0).unwrap().0 % 2;
+                    }
+                }
+            });
+        }
+    });
+    let mut components = acquire!(Read(First), Read(Second));
+    let (first, second) = components.get();
+    assert!(first.component_get(0).unwrap().0 == 4000);
+    assert!(second.component_get(0).unwrap().0 >= 4000);
+}
+
+/// Test that try_acquire! and acquire_timeout! give up instead of blocking
+#[test]
+fn try_acquire_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::static_storage::Error;
+    use std::collections::BTreeMap;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    impl_component!(SimpleTuple, BTreeMap<u64, SimpleTuple>);
+    struct SimpleTuple(u64);
+
+    impl_component!(SimpleTuple2, BTreeMap<u64, SimpleTuple2>);
+    struct SimpleTuple2();
+
+    let components = acquire!(Write(SimpleTuple));
+    thread::scope(|s| {
+        s.spawn(|| {
+            assert!(matches!(try_acquire!(Read(SimpleTuple2), Read(SimpleTuple)), Err(Error::IllegalReadGuardAcquire)));
+            // none of the storages are held after a failure
+            assert!(try_acquire!(Write(SimpleTuple2)).is_ok());
+            let now = Instant::now();
+            assert!(matches!(acquire_timeout!(Duration::from_millis(50), Write(SimpleTuple)), Err(Error::IllegalWriteGuardAcquire)));
+            assert!(now.elapsed().as_millis() >= 50);
+            // the storage is released while waiting
+            let mut components = acquire_timeout!(Duration::from_secs(10), Write(SimpleTuple), Read(SimpleTuple2)).unwrap();
+            let (st, _) = components.get();
+            st.component_insert(1, SimpleTuple(1));
+        });
+        thread::sleep(Duration::from_millis(100));
+        drop(components);
+    });
+    let mut components = try_acquire!(Read(SimpleTuple)).unwrap();
+    let (st,) = components.get();
+    assert!(st.component_get(1).unwrap().0 == 1);
+}
+
+/// Test that the hooks of a component are called by Entity::add, Entity::rm, the Commands and despawn,
+/// and that a hook can add another hook
+#[test]
+fn component_hooks_test(){
+    use genecs::component::Component;
+    use genecs::entity::{Entity, EntityID};
+    use genecs::commands::Commands;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Shape(u32);
+    impl_component!(Shape, BTreeMap<EntityID, Shape>);
+
+    lazy_static! {
+        static ref LOG : Mutex<Vec<String>> = Mutex::new(Vec::new());
+    }
+    Shape::hooks().on_add(|id, shape| LOG.lock().unwrap().push(format!("add {} {}", id.index(), shape.0)));
+    Shape::hooks().on_replace(|id, old, new| LOG.lock().unwrap().push(format!("replace {} {} {}", id.index(), old.0, new.0)));
+    Shape::hooks().on_remove(|id, shape| LOG.lock().unwrap().push(format!("remove {} {}", id.index(), shape.0)));
+
+    let (first, second) = {
+        let mut guard = acquire!(Write(Shape));
+        let (shapes,) = guard.get();
+        let first = Entity::new().add(*shapes, Shape(1)).add(*shapes, Shape(2)).get_id();
+        Entity::from(first).rm(*shapes);
+        // removing a component the entity doesn't have doesn't call the hooks
+        Entity::from(first).rm(*shapes);
+        (first, Entity::new().get_id())
+    };
+    let commands = Commands::new();
+    commands.add(second, Shape(3));
+    commands.despawn(second);
+    commands.apply();
+
+    let expected = vec![
+        format!("add {} 1", first.index()),
+        format!("replace {} 1 2", first.index()),
+        format!("remove {} 2", first.index()),
+        format!("add {} 3", second.index()),
+        format!("remove {} 3", second.index()),
+    ];
+    assert!(*LOG.lock().unwrap() == expected);
+
+    struct Marker;
+    impl_component!(Marker, BTreeMap<EntityID, Marker>);
+    static ADDED : AtomicUsize = AtomicUsize::new(0);
+    Marker::hooks().on_add(|_, _| Marker::hooks().on_add(|_, _| { ADDED.fetch_add(1, Ordering::SeqCst); }));
+    let mut guard = acquire!(Write(Marker));
+    let (markers,) = guard.get();
+    Entity::new().add(*markers, Marker);
+    assert!(ADDED.load(Ordering::SeqCst) == 0);
+    Entity::new().add(*markers, Marker);
+    assert!(ADDED.load(Ordering::SeqCst) == 1);
+}
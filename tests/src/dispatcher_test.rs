@@ -0,0 +1,125 @@
+/// Test that the Dispatcher puts systems that don't conflict in the same stage
+#[test]
+fn dispatcher_plan_test(){
+    use genecs::component::Component;
+    use genecs::resource::Resource;
+    use genecs::dispatcher::Dispatcher;
+    use genecs::system::{System, SystemData, Read, Write, Data};
+    use std::collections::BTreeMap;
+
+    impl_component!(Position, BTreeMap<u64, Position>);
+    struct Position();
+
+    impl_component!(Velocity, BTreeMap<u64, Velocity>);
+    struct Velocity();
+
+    struct Time();
+    impl_resource!(Time, Time());
+
+    /// A system that does nothing with the storages of D
+    struct Noop<D>(std::marker::PhantomData<D>);
+    impl<D : SystemData> System for Noop<D> {
+        type SystemData = D;
+        fn run(&mut self, _ : Data<Self>) {}
+    }
+    fn noop<D>() -> Noop<D> {
+        Noop(std::marker::PhantomData)
+    }
+
+    let dispatcher = Dispatcher::builder()
+        .with(noop::<(Write<Position>, Read<Velocity>)>(), "movement")
+        .with(noop::<Write<Velocity>>(), "acceleration")
+        .with(noop::<Read<Position>>(), "render")
+        .with(noop::<Read<Time>>(), "clock")
+        .with(noop::<(Write<Position>, Read<Time>)>(), "collision")
+        .build();
+    assert!(dispatcher.stages() == vec![vec!["movement", "clock"], vec!["acceleration", "render"], vec!["collision"]]);
+    assert!(dispatcher.to_string() == "stage 0: movement, clock\nstage 1: acceleration, render\nstage 2: collision\n");
+}
+
+/// Test that the Dispatcher runs conflicting systems in the order they were added
+#[test]
+fn dispatcher_order_test(){
+    use genecs::resource::Resource;
+    use genecs::dispatcher::Dispatcher;
+    use genecs::system::{System, Read, Write, Data};
+
+    struct Log(Vec<&'static str>);
+    impl_resource!(Log, Log(Vec::new()));
+
+    struct Counter(u64);
+    impl_resource!(Counter, Counter(0));
+
+    struct Append(&'static str);
+    impl System for Append {
+        type SystemData = Write<Log>;
+        fn run(&mut self, log : Data<Self>) {
+            log.0.push(self.0);
+        }
+    }
+
+    struct Count();
+    impl System for Count {
+        type SystemData = (Write<Counter>, Read<Log>);
+        fn run(&mut self, (counter, log) : Data<Self>) {
+            counter.0 += log.0.len() as u64;
+        }
+    }
+
+    let mut dispatcher = Dispatcher::builder()
+        .with(Append("first"), "first")
+        .with(Count(), "count")
+        .with(Append("second"), "second")
+        .build();
+    assert!(dispatcher.stages() == vec![vec!["first"], vec!["count"], vec!["second"]]);
+    dispatcher.dispatch();
+    dispatcher.dispatch();
+    let mut guard = acquire!(Read(Log), Read(Counter));
+    let (log, counter) = guard.get();
+    assert!(log.0 == vec!["first", "second", "first", "second"]);
+    assert!(counter.0 == 1 + 3);
+}
+
+/// Test that before and after constraints override the order systems were added in, and that cycles are reported
+#[test]
+fn dispatcher_constraint_test(){
+    use genecs::component::Component;
+    use genecs::dispatcher::{Dispatcher, Error};
+    use genecs::system::{System, SystemData, Read, Write, Data};
+    use std::collections::BTreeMap;
+
+    impl_component!(Mass, BTreeMap<u64, Mass>);
+    struct Mass();
+
+    struct Noop<D>(std::marker::PhantomData<D>);
+    impl<D : SystemData> System for Noop<D> {
+        type SystemData = D;
+        fn run(&mut self, _ : Data<Self>) {}
+    }
+    fn noop<D>() -> Noop<D> {
+        Noop(std::marker::PhantomData)
+    }
+
+    // gravity was added last, but must write the Mass before anything reads it
+    let dispatcher = Dispatcher::builder()
+        .with(noop::<Read<Mass>>(), "render").label("output")
+        .with(noop::<Read<Mass>>(), "audio").label("output")
+        .with(noop::<()>(), "log").after("output")
+        .with(noop::<Write<Mass>>(), "gravity").before("output")
+        .build();
+    assert!(dispatcher.stages() == vec![vec!["gravity"], vec!["render", "audio"], vec!["log"]]);
+
+    let cycle = Dispatcher::builder()
+        .with(noop::<()>(), "a").after("c")
+        .with(noop::<()>(), "b").after("a")
+        .with(noop::<()>(), "c").after("b")
+        .with(noop::<()>(), "d")
+        .try_build().err();
+    assert!(cycle == Some(Error::Cycle(vec!["a".to_string(), "b".to_string(), "c".to_string(), "a".to_string()])));
+    assert!(cycle.unwrap().to_string() == "The ordering of the systems contains a cycle: a -> b -> c -> a");
+
+    let unknown = Dispatcher::builder()
+        .with(noop::<()>(), "a").before("missing")
+        .try_build().err();
+    assert!(unknown == Some(Error::UnknownLabel { system: "a".to_string(), label: "missing".to_string() }));
+}
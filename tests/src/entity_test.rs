@@ -8,4 +8,104 @@ fn entity_test(){
 
     assert!(EntityRegister::get_new_id() == 0);
     assert!(EntityRegister::get_new_ids(5) == (1..6));
-}*/
\ No newline at end of file
+}*/
+/// Test that freed ids are no longer alive and that their index is
+/// reused with a new generation
+#[test]
+fn generation_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::{EntityID, EntityRegister, Entity};
+    use std::collections::BTreeMap;
+
+    impl_component!(SimpleTuple, BTreeMap<EntityID, SimpleTuple>);
+    struct SimpleTuple(u64);
+
+    let mut guard = acquire!(Write(SimpleTuple));
+    let (st,) = guard.get();
+    let entity = Entity::new().add(*st, SimpleTuple(1));
+    let id = entity.get_id();
+    assert!(entity.is_alive());
+    assert!(entity.get(*st).unwrap().0 == 1);
+
+    assert!(EntityRegister::free_id(id));
+    assert!(!EntityRegister::free_id(id), "an id was freed twice");
+    assert!(!entity.is_alive());
+    assert!(entity.get(*st).is_none(), "a stale entity returned a component");
+    assert!(st.component_get(id).is_some());
+
+    // other tests share the register, so the freed index may have been taken by them
+    let ids = EntityRegister::get_new_ids(100);
+    let reused = ids.iter().find(|new_id| new_id.index() == id.index());
+    match reused {
+        Some(new_id) => assert!(new_id.generation() > id.generation() && EntityRegister::is_alive(*new_id)),
+        None => assert!(!EntityRegister::is_alive(EntityID::new(id.index() as u32, id.generation() + 1))),
+    }
+    assert!(!EntityRegister::is_alive(id));
+}
+
+/// Test that despawning an entity removes all of it's components, both immediately
+/// and when queued from inside of a system
+#[test]
+fn despawn_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::{EntityID, EntityRegister, Entity};
+    use genecs::system::{System, Read, Data};
+    use std::collections::BTreeMap;
+
+    impl_component!(Position, BTreeMap<EntityID, Position>);
+    struct Position(u64);
+
+    impl_component!(Health, BTreeMap<EntityID, Health>);
+    struct Health(u64);
+
+    let (first, second, third) = {
+        let mut guard = acquire!(Write(Position), Write(Health));
+        let (pos, health) = guard.get();
+        let first = Entity::new().add(*pos, Position(1)).add(*health, Health(1)).get_id();
+        let second = Entity::new().add(*pos, Position(2)).add(*health, Health(0)).get_id();
+        let third = Entity::new().add(*pos, Position(3)).get_id();
+        (first, second, third)
+    };
+
+    assert!(Entity::from(first).despawn());
+    assert!(!EntityRegister::despawn(first), "an entity was despawned twice");
+
+    /// Despawns every entity that has no health left
+    struct DespawnDead();
+    impl System for DespawnDead {
+        type SystemData = Read<Health>;
+        fn run(&mut self, health : Data<Self>) {
+            for (id, value) in health.iter() {
+                if value.0 == 0 {
+                    Entity::from(id).despawn_later();
+                }
+            }
+        }
+    }
+    dispatch!(DespawnDead());
+    assert!(EntityRegister::is_alive(second), "a queued entity was despawned early");
+    EntityRegister::despawn_queued();
+
+    let mut guard = acquire!(Read(Position), Read(Health));
+    let (pos, health) = guard.get();
+    assert!(!EntityRegister::is_alive(first) && !EntityRegister::is_alive(second));
+    assert!(pos.component_get(first).is_none() && health.component_get(first).is_none());
+    assert!(pos.component_get(second).is_none() && health.component_get(second).is_none());
+    assert!(EntityRegister::is_alive(third) && pos.component_get(third).unwrap().0 == 3);
+}
+
+/// Test that clearing an EntityAllocator frees every id, and that the freed ids stay dead
+#[test]
+fn allocator_clear_test(){
+    use genecs::entity::{EntityID, EntityAllocator};
+
+    let allocator = EntityAllocator::new();
+    let ids = allocator.allocate_many(3);
+    assert!(allocator.free(ids[1]));
+    allocator.clear();
+    assert!(ids.iter().all(|id| !allocator.is_alive(*id)));
+    // the indices are reused from 0 with a new generation, so the ids from before the clear stay dead
+    let reused = allocator.allocate_many(3);
+    assert!(reused == vec![EntityID::new(0, 1), EntityID::new(1, 1), EntityID::new(2, 1)]);
+    assert!(ids.iter().all(|id| !allocator.is_alive(*id)));
+}
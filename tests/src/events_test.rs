@@ -0,0 +1,70 @@
+/// Test that every reader sees every event, that events are dropped once read or after two updates,
+/// and that a reader starts over when it's Events is reset
+#[test]
+fn events_test(){
+    use genecs::events::{Event, EventReader, UpdateEvents};
+    use genecs::system::{System, Read, Write, Data};
+
+    #[derive(PartialEq, Debug)]
+    struct Collision(u32);
+    impl_event!(Collision);
+
+    struct Collide(u32);
+    impl System for Collide {
+        type SystemData = Write<Collision>;
+        fn run(&mut self, events : Data<Self>) {
+            events.send(Collision(self.0));
+            self.0 += 1;
+        }
+    }
+
+    struct Listen(EventReader<Collision>, Vec<u32>);
+    impl System for Listen {
+        type SystemData = Read<Collision>;
+        fn run(&mut self, events : Data<Self>) {
+            self.1.extend(self.0.read(events).map(|c| c.0));
+        }
+    }
+
+    let mut update = UpdateEvents::<Collision>::new();
+    let mut collide = Collide(0);
+    let mut sound = Listen(EventReader::new(), Vec::new());
+    let mut score = Listen(EventReader::new(), Vec::new());
+
+    dispatch!(collide, collide, sound, update);
+    assert!(sound.1 == vec![0, 1]);
+    // score hasn't read the events yet, so they are kept
+    assert!(acquire!(Read(Collision)).get().0.len() == 2);
+
+    dispatch!(collide);
+    dispatch_parallel!(&mut sound, &mut score);
+    dispatch!(update);
+    assert!(sound.1 == vec![0, 1, 2]);
+    assert!(score.1 == vec![0, 1, 2]);
+    // every reader has read the events, so they were dropped by the update
+    assert!(acquire!(Read(Collision)).get().0.is_empty());
+
+    // an event that isn't read by every reader is dropped on the second update
+    let mut guard = acquire!(Write(Collision));
+    let (events,) = guard.get();
+    events.send(Collision(3));
+    events.update();
+    events.update();
+    assert!(events.is_empty());
+    let mut late = events.reader();
+    events.send(Collision(4));
+    assert!(late.read(events).collect::<Vec<_>>() == vec![&Collision(4)]);
+    assert!(late.read(events).next().is_none());
+    drop(guard);
+
+    reset!(Collision);
+    let mut guard = acquire!(Write(Collision));
+    let (events,) = guard.get();
+    events.send(Collision(5));
+    assert!(late.read(events).collect::<Vec<_>>() == vec![&Collision(5)]);
+    assert!(late.read(events).next().is_none());
+    events.send(Collision(6));
+    assert!(late.read(events).collect::<Vec<_>>() == vec![&Collision(6)]);
+    // a reader from before the reset that hasn't read since reads every event in the queue
+    assert!(sound.0.read(events).collect::<Vec<_>>() == vec![&Collision(5), &Collision(6)]);
+}
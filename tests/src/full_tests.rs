@@ -1,11 +1,11 @@
-#![allow(non_camel_case_types)]
+#![allow(non_camel_case_types, unused_doc_comments, clippy::bool_comparison, clippy::unnecessary_cast)]
 
 #[test]
 fn full_test(){
-    use kv_join;
     use genecs::component::{Component};
+    use genecs::join::Join;
     use genecs::entity::{EntityID, Entity};
-    use genecs::system::System;
+    use genecs::system::{System, Read, Write, Data};
     use std::collections::BTreeMap;
 
     /// Create several components
@@ -20,42 +20,43 @@ fn full_test(){
     impl_component!(CompD, BTreeMap<EntityID, CompD>);
 
     /// Create severl systems
-    struct InitializeEntities();
+    struct InitializeEntities(Vec<EntityID>);
     struct Add_Usize_System(usize);
     struct Add_CompD_If_CompA_and_CompB();
     struct Multiply_CompD_If_CompC(f64);
 
     /// This system creates 4 entities
     impl System for InitializeEntities{
-        fn run(&mut self) {
-            // acquire the storages that will be used to create the new entities
-            let mut guard = acquire!(Write(CompA), Write(CompB), Write(CompC));
-            let (a,b,c) = guard.get();
-
+        // the storages that will be used to create the new entities
+        type SystemData = (Write<CompA>, Write<CompB>, Write<CompC>);
+        fn run(&mut self, (a,b,c) : Data<Self>) {
             // create several entities
-            Entity::new()
-                .add(*a, CompA(1))
-                .add(*c, CompC(true));
-            Entity::new()
-                .add(*a, CompA(10))
-                .add(*b, CompB(0.5))
-                .add(*c, CompC(true));
-            Entity::new()
-                .add(*a, CompA(10))
-                .add(*b, CompB(0.5))
-                .add(*c, CompC(false));
-            Entity::new()
-                .add(*b, CompB(100.0005))
-                .add(*c, CompC(false));
+            let entity = Entity::new()
+                .add(a, CompA(1))
+                .add(c, CompC(true));
+            self.0.push(entity.get_id());
+            let entity = Entity::new()
+                .add(a, CompA(10))
+                .add(b, CompB(0.5))
+                .add(c, CompC(true));
+            self.0.push(entity.get_id());
+            let entity = Entity::new()
+                .add(a, CompA(10))
+                .add(b, CompB(0.5))
+                .add(c, CompC(false));
+            self.0.push(entity.get_id());
+            let entity = Entity::new()
+                .add(b, CompB(100.0005))
+                .add(c, CompC(false));
+            self.0.push(entity.get_id());
         }
     }
 
     /// This sytem takes all entities with a CompA component
     /// and adds a value to it
     impl System for Add_Usize_System {
-        fn run(&mut self) {
-            let mut guard = acquire!(Write(CompA));
-            let (a,) = guard.get();
+        type SystemData = Write<CompA>;
+        fn run(&mut self, a : Data<Self>) {
             for (_id, value) in a.iter_mut() {
                 value.0 += self.0;
             }
@@ -65,13 +66,12 @@ fn full_test(){
     /// This sytem takes all the entiities with a CompA and CompB
     /// Component and adds a new Component CompD which is the sum of the two
     impl System for Add_CompD_If_CompA_and_CompB {
-        fn run(&mut self) {
-            let mut guard = acquire!(Write(CompA), Write(CompB), Write(CompD));
-            let (a,b,d) = guard.get();
-            for (entity_id, (a_value, b_value)) in kvand_join!(a.iter_mut(), b.iter_mut()) {
+        type SystemData = (Read<CompA>, Read<CompB>, Write<CompD>);
+        fn run(&mut self, (a,b,d) : Data<Self>) {
+            for (entity_id, (a_value, b_value)) in (a, b).join() {
                 let d_value = a_value.0 as f64 + b_value.0 as f64;
                 let entity = Entity::from(entity_id);
-                entity.add(*d, CompD(d_value));
+                entity.add(d, CompD(d_value));
             }
         }
     }
@@ -79,10 +79,9 @@ fn full_test(){
     /// A system will take all the entities with a CompD and CompC
     /// Componnent and multiply the D component if the C component is true
     impl System for Multiply_CompD_If_CompC {
-        fn run(&mut self){
-            let mut guard = acquire!(Write(CompD), Read(CompC));
-            let (d,c) = guard.get();
-            for (_entity_id, (d_value, c_value)) in kvand_join!(d.iter_mut(), c.iter()){
+        type SystemData = (Write<CompD>, Read<CompC>);
+        fn run(&mut self, (d,c) : Data<Self>){
+            for (_entity_id, (d_value, c_value)) in (d, c).join() {
                 if c_value.0 {
                     d_value.0 *= self.0
                 }
@@ -91,7 +90,7 @@ fn full_test(){
     }
 
     // Create the actual instance of the systems
-    let mut sys1 = InitializeEntities();
+    let mut sys1 = InitializeEntities(Vec::new());
     let mut sys2 = Add_Usize_System(10);
     let mut sys3 = Add_CompD_If_CompA_and_CompB();
     let mut sys4 = Multiply_CompD_If_CompC(2.0);
@@ -103,7 +102,7 @@ fn full_test(){
     let mut guard = acquire!(Read(CompA), Read(CompB), Read(CompC), Read(CompD));
     let (a_s,b_s,c_s,d_s) = guard.get();
     // check the first entity
-    let entity = Entity::from(0);
+    let entity = Entity::from(sys1.0[0]);
     let a = entity.get(*a_s).expect("A value was unexpectedly none");
     let b = entity.get(*b_s);
     let c = entity.get(*c_s).expect("C value was unexpectedly none");
@@ -113,7 +112,7 @@ fn full_test(){
     assert!(c.0 == true);
     assert!(d.is_none());
     // check the second entity
-    let entity = Entity::from(1);
+    let entity = Entity::from(sys1.0[1]);
     let a = entity.get(*a_s).expect("A value was unexpectedly none");
     let b = entity.get(*b_s).expect("B value was unexpectedly none");
     let c = entity.get(*c_s).expect("C value was unexpectedly none");
@@ -123,7 +122,7 @@ fn full_test(){
     assert!(c.0 == true);
     assert!(d.0 == 41.0);
     // check the thrd entity
-    let entity = Entity::from(2);
+    let entity = Entity::from(sys1.0[2]);
     let a = entity.get(*a_s).expect("A value was unexpectedly none");
     let b = entity.get(*b_s).expect("B value was unexpectedly none");
     let c = entity.get(*c_s).expect("C value was unexpectedly none");
@@ -133,7 +132,7 @@ fn full_test(){
     assert!(c.0 == false);
     assert!(d.0 == 20.5);
     // check the fourth entity
-    let entity = Entity::from(3);
+    let entity = Entity::from(sys1.0[3]);
     let a = entity.get(*a_s);
     let b = entity.get(*b_s).expect("B value was unexpectedly none");
     let c = entity.get(*c_s).expect("C value was unexpectedly none");
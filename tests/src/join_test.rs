@@ -0,0 +1,153 @@
+/// Test that joining storages only yields the entities that are in every storage
+/// and that the entities are yielded in order
+#[test]
+fn join_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::join::Join;
+    use std::collections::BTreeMap;
+
+    impl_component!(SimpleTuple, BTreeMap<u64, SimpleTuple>);
+    struct SimpleTuple(u64);
+
+    impl_component!(SimpleTuple2, BTreeMap<u64, SimpleTuple2>);
+    struct SimpleTuple2(u64);
+
+    let mut components = acquire!(Write(SimpleTuple), Write(SimpleTuple2));
+    let (st, st2) = components.get();
+    for i in 0..20 {
+        st.component_insert(i, SimpleTuple(i));
+        if i % 3 == 0 {
+            st2.component_insert(i, SimpleTuple2(0));
+        }
+    }
+    st2.component_insert(25, SimpleTuple2(0));
+
+    // modify the second storage through the join
+    for (k, (v1, v2)) in (&**st, &mut **st2).join() {
+        assert!(k == v1.0, "joined components belong to different entities");
+        v2.0 = v1.0 * 2;
+    }
+
+    let keys : Vec<u64> = (&**st2,).join().map(|(k, _)| k).collect();
+    assert!(keys == vec![0, 3, 6, 9, 12, 15, 18, 25]);
+    let values : Vec<u64> = st2.component_iter().map(|(_, v)| v.0).collect();
+    assert!(values == vec![0, 6, 12, 18, 24, 30, 36, 0]);
+}
+
+/// Test joining directly on the guard returned by acquire!
+#[test]
+fn guard_join_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use std::collections::BTreeMap;
+
+    impl_component!(SimpleTuple, BTreeMap<u64, SimpleTuple>);
+    struct SimpleTuple(u64);
+
+    impl_component!(SimpleTuple2, BTreeMap<u64, SimpleTuple2>);
+    struct SimpleTuple2(u64);
+
+    {
+        let mut components = acquire!(Write(SimpleTuple), Write(SimpleTuple2));
+        let (st, st2) = components.get();
+        st.component_insert(1, SimpleTuple(1));
+        st.component_insert(2, SimpleTuple(2));
+        st2.component_insert(2, SimpleTuple2(5));
+        st2.component_insert(3, SimpleTuple2(6));
+    }
+
+    let mut components = acquire!(Read(SimpleTuple), Write(SimpleTuple2));
+    let mut iter = components.join();
+    let (k, (v1, v2)) = iter.next().unwrap();
+    assert!(k == 2 && v1.0 == 2 && v2.0 == 5);
+    v2.0 = 7;
+    assert!(iter.next().is_none());
+}
+
+/// Test joining with Optional and Not storages, and that a join of only wrapped storages yields nothing
+#[test]
+fn optional_not_join_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::join::{Join, Optional, Not};
+    use std::collections::BTreeMap;
+
+    impl_component!(Health, BTreeMap<u64, Health>);
+    struct Health(u64);
+
+    impl_component!(Armor, BTreeMap<u64, Armor>);
+    struct Armor(u64);
+
+    impl_component!(Dead, BTreeMap<u64, Dead>);
+    struct Dead();
+
+    let mut components = acquire!(Write(Health), Write(Armor), Write(Dead));
+    let (health, armor, dead) = components.get();
+    for i in 0..10 {
+        health.component_insert(i, Health(i));
+    }
+    armor.component_insert(2, Armor(20));
+    armor.component_insert(5, Armor(50));
+    armor.component_insert(11, Armor(110));
+    dead.component_insert(0, Dead());
+    dead.component_insert(5, Dead());
+    dead.component_insert(9, Dead());
+
+    let joined : Vec<(u64, Option<u64>)> = (&**health, Optional(&**armor), Not(&**dead)).join()
+        .map(|(k, (h, a, ()))| {
+            assert!(k == h.0);
+            (k, a.map(|a| a.0))
+        })
+        .collect();
+    assert!(joined == vec![(1, None), (2, Some(20)), (3, None), (4, None), (6, None), (7, None), (8, None)]);
+
+    // the optional component can be mutated through the join
+    for (_, (a, _)) in (Optional(&mut **armor), &**dead).join() {
+        if let Some(a) = a {
+            a.0 = 0;
+        }
+    }
+    assert!(armor.component_get(5).unwrap().0 == 0);
+    assert!(armor.component_get(2).unwrap().0 == 20);
+
+    assert!((Optional(&**armor), Not(&**dead)).join().next().is_none());
+}
+
+/// Test HashMap storages, joining them in order and looking them up with Lookup
+#[test]
+fn hash_map_join_test(){
+    use genecs::component::{Component, ComponentStorage, FastHashMap};
+    use genecs::entity::EntityID;
+    use genecs::join::{Join, Lookup};
+    use genecs::storage::VecStorage;
+    use std::collections::HashMap;
+
+    impl_component!(Selected, HashMap<EntityID, Selected>);
+    struct Selected(u32);
+
+    impl_component!(Highlight, FastHashMap<EntityID, Highlight>);
+    struct Highlight(u32);
+
+    impl_component!(Position, VecStorage<Position>);
+    struct Position(u32);
+
+    let mut guard = acquire!(Write(Selected), Write(Highlight), Write(Position));
+    let (selected, highlight, position) = guard.get();
+    for index in (0..1000).rev() {
+        position.component_insert(EntityID::new(index, 0), Position(index));
+    }
+    for index in [900, 5, 77] {
+        selected.component_insert(EntityID::new(index, 0), Selected(index));
+        highlight.component_insert(EntityID::new(index, 0), Highlight(0));
+    }
+    selected.component_insert(EntityID::new(5000, 0), Selected(5000));
+
+    // iterating a HashMap is sorted so that it can be merged
+    let keys : Vec<usize> = (&**selected, &**position).join().map(|(id, _)| id.index()).collect();
+    assert!(keys == vec![5, 77, 900]);
+
+    // the highlighted entities drive the join and each one is looked up in the other storages
+    for (_, (h, s, p)) in (&mut **highlight, Lookup(&**selected), Lookup(&**position)).join() {
+        h.0 = s.0 + p.0;
+    }
+    let values : Vec<u32> = highlight.component_iter().map(|(_, h)| h.0).collect();
+    assert!(values == vec![10, 154, 1800]);
+}
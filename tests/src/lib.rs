@@ -1,11 +1,16 @@
 #[allow(unused_imports)]
 #[macro_use] extern crate genecs;
 #[allow(unused_imports)]
-#[macro_use] extern crate kv_join;
-#[allow(unused_imports)]
 #[macro_use] extern crate lazy_static;
 mod component_test;
 mod resource_test;
 mod system_test;
+mod dispatcher_test;
+mod commands_test;
+mod events_test;
+mod thread_pool_test;
 mod entity_test;
+mod join_test;
+mod storage_test;
+mod world_test;
 mod full_tests;
\ No newline at end of file
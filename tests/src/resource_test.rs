@@ -13,4 +13,95 @@ fn component_test(){
     let mut guard = acquire!(Read(SimpleTuple));
     let (res,) = guard.get();
     assert!(res.0 == 5);
-}
\ No newline at end of file
+}
+
+/// Test that reset! sets resources back to their initial value, both the static ones and the ones of a World
+#[test]
+fn reset_test(){
+    use genecs::resource::Resource;
+    use genecs::world::World;
+
+    struct SimpleTuple(u64);
+    impl_resource!(SimpleTuple, SimpleTuple(3));
+
+    let mut guard = acquire!(Write(SimpleTuple));
+    let (res,) = guard.get();
+    res.0 = 5;
+    drop(guard);
+    reset!(SimpleTuple);
+    let mut guard = acquire!(Read(SimpleTuple));
+    let (res,) = guard.get();
+    assert!(res.0 == 3);
+    drop(guard);
+
+    let world = World::new();
+    let mut guard = acquire!(world => Write(SimpleTuple));
+    let (res,) = guard.get();
+    res.0 = 7;
+    drop(guard);
+    reset!(world => SimpleTuple);
+    let mut guard = acquire!(world => Read(SimpleTuple));
+    let (res,) = guard.get();
+    assert!(res.0 == 3);
+}
+
+/// Test that accounting errors are returned by Guard::release instead of panicking
+#[test]
+fn release_error_test(){
+    use genecs::resource::Resource;
+    use genecs::static_storage::{StaticStorage, Error};
+
+    struct Counter(u64);
+    impl_resource!(Counter, Counter(0));
+
+    struct Other(u64);
+    impl_resource!(Other, Other(0));
+
+    let mut guard = acquire_checked!(Read(Counter), Write(Other)).unwrap();
+    let (counter, other) = guard.get();
+    other.0 = counter.0 + 1;
+    // simulate an accounting bug by releasing the reader behind the guard's back
+    unsafe { assert!(Counter::release_read_guard().is_ok()) };
+    let error = guard.release().unwrap_err();
+    assert!(error == Error::UnacountedGuard);
+    assert!(error.to_string() == "Attempted to release a guard that the static storage storage was unaware of.");
+    // the other storage was still released
+    let mut guard = try_acquire!(Write(Other)).unwrap();
+    let (other,) = guard.get();
+    assert!(other.0 == 1);
+    drop(guard);
+
+    // dropping a guard with an accounting error doesn't panic
+    let guard = acquire!(Write(Counter));
+    unsafe { assert!(Counter::release_write_guard().is_ok()) };
+    drop(guard);
+    let _error : Box<dyn std::error::Error> = Box::new(Error::AliasingDetected);
+}
+
+/// Test that requesting a storage twice with a write returns AliasingDetected instead of blocking forever
+#[test]
+fn aliasing_request_test(){
+    use genecs::resource::Resource;
+    use genecs::static_storage::Error;
+    use genecs::system::{System, Read, Write, Data};
+    use std::time::Duration;
+
+    struct Shared;
+    impl_resource!(Shared, Shared);
+
+    assert!(matches!(acquire_checked!(Write(Shared), Read(Shared)), Err(Error::AliasingDetected)));
+    assert!(matches!(acquire_checked!(Write(Shared), Write(Shared)), Err(Error::AliasingDetected)));
+    assert!(matches!(try_acquire!(Read(Shared), Write(Shared)), Err(Error::AliasingDetected)));
+    assert!(matches!(acquire_timeout!(Duration::from_secs(10), Write(Shared), Read(Shared)), Err(Error::AliasingDetected)));
+    // reading a storage twice is still allowed, and nothing was left acquired
+    assert!(acquire_checked!(Read(Shared), Read(Shared)).is_ok());
+    assert!(try_acquire!(Write(Shared)).is_ok());
+
+    struct Aliasing;
+    impl System for Aliasing {
+        type SystemData = (Write<Shared>, Read<Shared>);
+        fn run(&mut self, _ : Data<Self>) {}
+    }
+    assert!(std::panic::catch_unwind(|| Aliasing.dispatch()).is_err());
+    assert!(try_acquire!(Write(Shared)).is_ok());
+}
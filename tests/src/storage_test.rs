@@ -0,0 +1,316 @@
+/// Test that a FlaggedStorage records changes for each reader, and that Changed and Added filter joins
+#[test]
+fn flagged_storage_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::{Entity, EntityID};
+    use genecs::join::Join;
+    use genecs::storage::{FlaggedStorage, Changed, Added, Tracked};
+    use std::collections::BTreeMap;
+
+    struct Position(i32);
+    impl_component!(Position, FlaggedStorage<BTreeMap<EntityID, Position>, Position>);
+
+    let mut guard = acquire!(Write(Position));
+    let (positions,) = guard.get();
+    let mut network = positions.reader();
+    let first = Entity::new().add(*positions, Position(1)).get_id();
+    let second = Entity::new().add(*positions, Position(2)).get_id();
+    let mut render = positions.reader();
+    let third = Entity::new().add(*positions, Position(3)).get_id();
+
+    let changes = positions.changes(&mut network);
+    assert!(changes.inserted().copied().collect::<Vec<_>>() == vec![first, second, third]);
+    let changes = positions.changes(&mut render);
+    assert!(changes.inserted().copied().collect::<Vec<_>>() == vec![third]);
+
+    positions.component_get_mut(first).unwrap().0 += 10;
+    Entity::from(second).rm(*positions);
+    let fourth = Entity::new().add(*positions, Position(4)).get_id();
+    let changes = positions.changes(&mut network);
+    assert!(changes.modified().copied().collect::<Vec<_>>() == vec![first]);
+    assert!(changes.removed().copied().collect::<Vec<_>>() == vec![second]);
+    let changed : Vec<(EntityID, i32)> = (&**positions, Changed(&changes)).join().map(|(id, (p, ()))| (id, p.0)).collect();
+    assert!(changed == vec![(first, 11), (fourth, 4)]);
+    let added : Vec<EntityID> = (&**positions, Added(&changes)).join().map(|(id, _)| id).collect();
+    assert!(added == vec![fourth]);
+
+    // iterating mutably marks every component as modified
+    for (_, position) in (&mut **positions,).join() {
+        position.0.0 *= 2;
+    }
+    assert!(positions.changes(&mut network).modified().count() == 3);
+    assert!(positions.changes(&mut network).is_empty());
+
+    // a join of Tracked only records the components it yields
+    struct Velocity(i32);
+    impl_component!(Velocity, BTreeMap<EntityID, Velocity>);
+    let mut velocity_guard = acquire!(Write(Velocity));
+    let (velocities,) = velocity_guard.get();
+    velocities.component_insert(third, Velocity(5));
+    for (_, (position, velocity)) in (Tracked(&mut **positions), &**velocities).join() {
+        position.0 += velocity.0;
+    }
+    assert!(positions.changes(&mut network).modified().copied().collect::<Vec<_>>() == vec![third]);
+
+    // every change has been read by network, and render's changes are dropped after two updates
+    positions.update();
+    positions.update();
+    assert!(positions.changes(&mut render).is_empty());
+}
+
+/// Test that a VecStorage finds components by index, checks generations, ignores stale ids, and joins in order
+#[test]
+fn vec_storage_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::EntityID;
+    use genecs::join::Join;
+    use genecs::storage::VecStorage;
+    use std::collections::BTreeMap;
+
+    struct Velocity(i32);
+    impl_component!(Velocity, VecStorage<Velocity>);
+
+    struct Name(&'static str);
+    impl_component!(Name, BTreeMap<EntityID, Name>);
+
+    let mut guard = acquire!(Write(Velocity), Write(Name));
+    let (velocities, names) = guard.get();
+    for index in [130, 3, 64, 0, 7] {
+        velocities.component_insert(EntityID::new(index, 0), Velocity(index as i32));
+    }
+    velocities.component_remove(&EntityID::new(7, 0));
+    // a newer generation replaces the component, and the old id no longer finds it
+    velocities.component_insert(EntityID::new(3, 1), Velocity(-3));
+    assert!(velocities.component_get(EntityID::new(3, 0)).is_none());
+    assert!(velocities.component_get(EntityID::new(3, 1)).unwrap().0 == -3);
+    assert!(velocities.component_get(EntityID::new(500, 0)).is_none());
+    // an older generation doesn't replace the component of the entity that reused the index
+    velocities.component_insert(EntityID::new(3, 0), Velocity(99));
+    assert!(velocities.component_get(EntityID::new(3, 0)).is_none());
+    assert!(velocities.component_get(EntityID::new(3, 1)).unwrap().0 == -3);
+    assert!(velocities.len() == 4);
+
+    for (_, velocity) in velocities.component_iter_mut() {
+        velocity.0 *= 2;
+    }
+    let values : Vec<(usize, i32)> = velocities.component_iter().map(|(id, v)| (id.index(), v.0)).collect();
+    assert!(values == vec![(0, 0), (3, -6), (64, 128), (130, 260)]);
+
+    names.component_insert(EntityID::new(3, 1), Name("three"));
+    names.component_insert(EntityID::new(64, 0), Name("sixty four"));
+    names.component_insert(EntityID::new(100, 0), Name("hundred"));
+    let joined : Vec<(&str, i32)> = (&**names, &mut **velocities).join().map(|(_, (n, v))| (n.0, v.0)).collect();
+    assert!(joined == vec![("three", -6), ("sixty four", 128)]);
+}
+
+/// Test that a SparseSet stays packed when removing, ignores stale ids, and iterates sorted by EntityID for joins
+#[test]
+fn sparse_set_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::EntityID;
+    use genecs::join::Join;
+    use genecs::storage::{SparseSet, VecStorage};
+
+    struct Sprite(u32);
+    impl_component!(Sprite, SparseSet<Sprite>);
+
+    struct Visible();
+    impl_component!(Visible, VecStorage<Visible>);
+
+    let mut guard = acquire!(Write(Sprite), Write(Visible));
+    let (sprites, visible) = guard.get();
+    for index in 0..6 {
+        sprites.component_insert(EntityID::new(index, 0), Sprite(index));
+    }
+    assert!(sprites.is_sorted());
+    sprites.component_remove(&EntityID::new(1, 0));
+    assert!(sprites.swap_remove(EntityID::new(1, 0)).is_none());
+    // the last component was moved into the removed position
+    assert!(sprites.as_slice().iter().map(|s| s.0).collect::<Vec<_>>() == vec![0, 5, 2, 3, 4]);
+    assert!(sprites.entities()[1] == EntityID::new(5, 0));
+    assert!(!sprites.is_sorted());
+    assert!(sprites.component_get(EntityID::new(5, 0)).unwrap().0 == 5);
+
+    // a newer generation replaces the component, while an older one is ignored
+    sprites.component_insert(EntityID::new(3, 1), Sprite(3));
+    sprites.component_insert(EntityID::new(3, 0), Sprite(99));
+    assert!(sprites.component_get(EntityID::new(3, 0)).is_none());
+    assert!(sprites.component_get(EntityID::new(3, 1)).unwrap().0 == 3);
+
+    // iterating follows the sparse array while the dense Vec isn't sorted
+    let order : Vec<u32> = sprites.component_iter().map(|(_, s)| s.0).collect();
+    assert!(order == vec![0, 2, 3, 4, 5]);
+
+    for slot in sprites.as_mut_slice() {
+        slot.0 *= 10;
+    }
+    visible.component_insert(EntityID::new(5, 0), Visible());
+    visible.component_insert(EntityID::new(2, 0), Visible());
+    let joined : Vec<(EntityID, u32)> = (&mut **sprites, &**visible).join().map(|(id, (s, _))| (id, s.0)).collect();
+    assert!(joined == vec![(EntityID::new(2, 0), 20), (EntityID::new(5, 0), 50)]);
+    // iterating mutably sorted the dense Vec
+    assert!(sprites.is_sorted());
+    assert!(sprites.as_slice().iter().map(|s| s.0).collect::<Vec<_>>() == vec![0, 20, 30, 40, 50]);
+}
+
+/// Test that a NullStorage tags entities through Entity::add and rm, ignores stale ids, and works in joins
+#[test]
+fn null_storage_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::{Entity, EntityID};
+    use genecs::join::{Join, Not};
+    use genecs::storage::{NullStorage, TagStorage};
+    use std::collections::BTreeMap;
+
+    struct Frozen;
+    impl_component!(Frozen, NullStorage<Frozen>);
+
+    struct Player;
+    impl_component!(Player, TagStorage<Player>);
+
+    struct Health(u32);
+    impl_component!(Health, BTreeMap<EntityID, Health>);
+
+    let mut guard = acquire!(Write(Frozen), Write(Player), Write(Health));
+    let (frozen, player, health) = guard.get();
+    let first = Entity::new().add(*health, Health(1)).add(*frozen, Frozen).add(*player, Player).get_id();
+    let second = Entity::new().add(*health, Health(2)).add(*frozen, Frozen).get_id();
+    let third = Entity::new().add(*health, Health(3)).get_id();
+    Entity::from(second).rm(*frozen);
+    assert!(frozen.len() == 1 && frozen.contains(first) && !frozen.contains(second));
+    assert!(frozen.component_get(EntityID::new(first.index() as u32, first.generation() + 1)).is_none());
+    // an older generation doesn't untag the entity that reused the index
+    let reused = EntityID::new(first.index() as u32, first.generation() + 1);
+    frozen.component_insert(reused, Frozen);
+    frozen.component_insert(first, Frozen);
+    assert!(frozen.contains(reused) && !frozen.contains(first));
+    frozen.component_remove(&reused);
+    frozen.component_insert(first, Frozen);
+
+    let thawed : Vec<EntityID> = (&**health, Not(&**frozen)).join().map(|(id, _)| id).collect();
+    assert!(thawed == vec![second, third]);
+    let players : Vec<u32> = (&**health, &mut **player).join().map(|(_, (h, _))| h.0).collect();
+    assert!(players == vec![1]);
+}
+
+/// Test that Archetypes moves entities between archetypes as components are added and removed, and that queries
+/// visit every matching archetype
+#[test]
+fn archetypes_test(){
+    use genecs::entity::{Entity, EntityID, EntityRegister};
+    use genecs::storage::Archetypes;
+
+    struct Position(f32);
+    struct Velocity(f32);
+    struct Frozen;
+
+    let mut archetypes = Archetypes::new();
+    let ids : Vec<EntityID> = (0..4).map(|_| Entity::new().get_id()).collect();
+    for (i, id) in ids.iter().enumerate() {
+        archetypes.insert(*id, Position(i as f32));
+        archetypes.insert(*id, Velocity(1.0));
+    }
+    archetypes.insert(ids[3], Frozen);
+    assert!(archetypes.remove::<Velocity>(ids[0]).is_some_and(|v| v.0 == 1.0));
+    assert!(archetypes.len() == 4 && archetypes.archetypes().len() == 3);
+
+    // ids[0] no longer has a Velocity, and ids[3] is in the archetype with Frozen
+    for (_, (position, velocity)) in archetypes.query::<(&mut Position, &Velocity)>() {
+        position.0 += velocity.0;
+    }
+    let mut moved : Vec<(EntityID, f32)> = archetypes.iter::<(&Position, &Velocity)>().map(|(id, (p, _))| (id, p.0)).collect();
+    moved.sort_by_key(|(id, _)| *id);
+    assert!(moved == vec![(ids[1], 2.0), (ids[2], 3.0), (ids[3], 4.0)]);
+    assert!(archetypes.get::<Position>(ids[0]).is_some_and(|p| p.0 == 0.0));
+    assert!(archetypes.chunks::<(&Frozen, &mut Velocity)>().map(|(entities, _)| entities.to_vec()).collect::<Vec<_>>() == vec![vec![ids[3]]]);
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { archetypes.query::<(&mut Position, &Position)>(); })).is_err());
+
+    // despawning an entity removes it from the static Archetypes
+    let entity = Entity::new().get_id();
+    acquire!(Write(Archetypes)).get().0.insert(entity, Position(5.0));
+    assert!(EntityRegister::despawn(entity));
+    assert!(!acquire!(Read(Archetypes)).get().0.contains(entity));
+}
+
+/// Test that an IndexedStorage finds entities by key as components are inserted, mutated and removed
+#[test]
+fn indexed_storage_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::{Entity, EntityID};
+    use genecs::join::Join;
+    use genecs::storage::{IndexedStorage, IndexKey};
+    use std::collections::BTreeMap;
+
+    struct Name(String);
+    struct ByName;
+    impl IndexKey<Name> for ByName {
+        type Key = String;
+        fn key(name : &Name) -> String { name.0.clone() }
+    }
+    impl_component!(Name, IndexedStorage<BTreeMap<EntityID, Name>, Name, ByName>);
+
+    let mut guard = acquire!(Write(Name));
+    let (names,) = guard.get();
+    let door = Entity::new().add(*names, Name("door".to_string())).get_id();
+    let window = Entity::new().add(*names, Name("window".to_string())).get_id();
+    let other_door = Entity::new().add(*names, Name("door".to_string())).get_id();
+    assert!(names.lookup("door") == vec![door, other_door]);
+    assert!(names.lookup_first("window") == Some(window));
+
+    // a mutated key is found before the index is updated
+    names.component_get_mut(other_door).unwrap().0 = "gate".to_string();
+    assert!(names.lookup("door") == vec![door] && names.lookup("gate") == vec![other_door]);
+    for (_, name) in (&mut **names,).join() {
+        name.0.0.push('s');
+    }
+    assert!(names.lookup("windows") == vec![window] && !names.contains_key("window"));
+    Entity::from(door).rm(*names);
+    assert!(names.lookup("doors").is_empty() && names.lookup("gates") == vec![other_door]);
+    drop(guard);
+
+    // a read guard is enough to look up entities
+    assert!(acquire!(Read(Name)).get().0.lookup_first("windows") == Some(window));
+}
+
+/// Test that a UniqueStorage keeps the component on one entity, and that single finds it
+#[test]
+fn unique_storage_test(){
+    use genecs::component::Component;
+    use genecs::entity::{Entity, EntityID};
+    use genecs::join::{Join, SingleError};
+    use genecs::storage::{UniqueStorage, UniqueError, Reject, Panic};
+    use std::collections::BTreeMap;
+
+    struct MainCamera(u32);
+    impl_component!(MainCamera, UniqueStorage<MainCamera>);
+
+    struct ActivePlayer;
+    impl_component!(ActivePlayer, UniqueStorage<ActivePlayer, Reject>);
+
+    struct Boss;
+    impl_component!(Boss, UniqueStorage<Boss, Panic>);
+
+    struct Enemy;
+    impl_component!(Enemy, BTreeMap<EntityID, Enemy>);
+
+    let mut guard = acquire!(Write(MainCamera), Write(ActivePlayer), Write(Boss), Write(Enemy));
+    let (camera, player, boss, enemy) = guard.get();
+    assert!(matches!((&**camera,).single(), Err(SingleError::NoEntities(_))));
+    let first = Entity::new().add(*camera, MainCamera(1)).add(*player, ActivePlayer).get_id();
+    let second = Entity::new().add(*camera, MainCamera(2)).get_id();
+    assert!(camera.get().is_some_and(|(id, c)| id == second && c.0 == 2));
+    assert!((&**camera,).single().is_ok_and(|(id, c)| id == second && c.0.0 == 2));
+    assert!(player.try_insert(second, ActivePlayer).is_err_and(|(error, _)| error.existing == first));
+    Entity::from(second).add(*player, ActivePlayer);
+    assert!(player.entity() == Some(first));
+    assert!(player.take_rejected() == [UniqueError { name: std::any::type_name::<ActivePlayer>(), existing: first, rejected: second }]);
+    assert!(player.take_rejected().is_empty());
+    Entity::from(first).add(*boss, Boss);
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { Entity::from(second).add(*boss, Boss); })).is_err());
+
+    Entity::new().add(*enemy, Enemy);
+    Entity::new().add(*enemy, Enemy);
+    let error = (&**enemy,).single().err().unwrap();
+    assert!(matches!(error, SingleError::MultipleEntities(_, 2)));
+    assert!(error.to_string().ends_with("but there were 2"));
+}
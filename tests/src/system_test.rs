@@ -13,7 +13,8 @@ fn system_test(){
     }
 
     impl System for Mather {
-        fn run(&mut self) {
+        type SystemData = ();
+        fn run(&mut self, _ : ()) {
             // needed to modify static variable
             unsafe {
                 match self {
@@ -48,7 +49,8 @@ fn system_par_test(){
     }
 
     impl System for Mather {
-        fn run(&mut self) {
+        type SystemData = ();
+        fn run(&mut self, _ : ()) {
             match self {
                 Mather::Add(i) => {
                     let val = SYSTEM_TEST.lock();
@@ -71,4 +73,104 @@ fn system_par_test(){
     assert!(*SYSTEM_TEST.lock().unwrap() == 6);
     dispatch!(add_one,add_two,add_three);
     assert!(*SYSTEM_TEST.lock().unwrap() == 12);
-}
\ No newline at end of file
+}
+
+/// Test that systems are given the storages of their SystemData
+#[test]
+fn system_data_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::resource::Resource;
+    use genecs::static_storage::Access;
+    use genecs::system::{System, SystemData, Read, Write, Data};
+    use std::any::TypeId;
+    use std::collections::BTreeMap;
+
+    impl_component!(Velocity, BTreeMap<u64, Velocity>);
+    struct Velocity(u64);
+
+    struct Time(u64);
+    impl_resource!(Time, Time(2));
+
+    struct Move();
+    impl System for Move {
+        type SystemData = (Write<Velocity>, Read<Time>);
+        fn run(&mut self, (velocity, time) : Data<Self>) {
+            for (_, value) in velocity.component_iter_mut() {
+                value.0 *= time.0;
+            }
+        }
+    }
+
+    let accesses = <Move as System>::SystemData::accesses();
+    assert!(accesses == vec![(TypeId::of::<Velocity>(), Access::Write), (TypeId::of::<Time>(), Access::Read)]);
+
+    {
+        let mut guard = acquire!(Write(Velocity));
+        let (velocity,) = guard.get();
+        velocity.component_insert(1, Velocity(3));
+    }
+    let mut system = Move();
+    dispatch!(system, system);
+    // the storages are released after the system runs
+    let mut guard = try_acquire!(Write(Velocity), Write(Time)).unwrap();
+    let (velocity, _) = guard.get();
+    assert!(velocity.component_get(1).unwrap().0 == 12);
+}
+
+/// Test the run criteria and fixed stages of a Schedule
+#[test]
+fn schedule_test(){
+    use genecs::resource::Resource;
+    use genecs::dispatcher::Dispatcher;
+    use genecs::system::{System, Schedule, FixedTime, Write, Data, every_n_ticks, resource_is};
+    use std::time::Duration;
+
+    struct Paused(bool);
+    impl_resource!(Paused, Paused(false));
+
+    struct Counts { frames: u64, physics: u64, collisions: u64, autosave: u64 }
+    impl_resource!(Counts, Counts { frames: 0, physics: 0, collisions: 0, autosave: 0 });
+
+    struct Count(fn(&mut Counts) -> &mut u64);
+    impl System for Count {
+        type SystemData = Write<Counts>;
+        fn run(&mut self, counts : Data<Self>) {
+            *(self.0)(counts) += 1;
+        }
+    }
+
+    let mut schedule = Schedule::new()
+        .with_stage("frame", Dispatcher::builder()
+            .with(Count(|c| &mut c.frames), "frame")
+            .with(Count(|c| &mut c.autosave), "autosave").run_if(every_n_ticks(3))
+            .build())
+        .with_fixed_stage("physics", Dispatcher::builder().with(Count(|c| &mut c.physics), "physics").build())
+        .run_if(resource_is::<Paused, _>(|paused| !paused.0))
+        .with_fixed_stage("collisions", Dispatcher::builder().with(Count(|c| &mut c.collisions), "collisions").build());
+    assert!(schedule.stages() == vec!["frame", "physics", "collisions"]);
+
+    {
+        let mut guard = acquire!(Write(FixedTime));
+        let (time,) = guard.get();
+        time.step = Duration::from_millis(10);
+        time.accumulate(Duration::from_millis(25));
+    }
+    schedule.run();
+    acquire!(Write(Paused)).get().0.0 = true;
+    acquire!(Write(FixedTime)).get().0.accumulate(Duration::from_millis(10));
+    schedule.run();
+    acquire!(Write(Paused)).get().0.0 = false;
+    // more steps than max_steps, the extra steps are dropped
+    acquire!(Write(FixedTime)).get().0.accumulate(Duration::from_millis(100));
+    schedule.run();
+    schedule.run();
+
+    let mut guard = acquire!(Read(Counts), Read(FixedTime));
+    let (counts, time) = guard.get();
+    assert!(counts.frames == 4);
+    assert!(counts.autosave == 2);
+    // every fixed stage runs for the same steps, collisions also ran for the step taken while physics was paused
+    assert!(counts.physics == 2 + 8);
+    assert!(counts.collisions == 2 + 1 + 8);
+    assert!(time.accumulator == Duration::from_millis(5));
+}
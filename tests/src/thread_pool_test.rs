@@ -0,0 +1,58 @@
+/// Test that tasks spawned in a scope can borrow from the stack and have finished once scope returns
+#[test]
+fn thread_pool_scope_test(){
+    use genecs::thread_pool::ThreadPool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let pool = ThreadPool::new(2);
+    assert!(pool.threads() == 2);
+    let mut values = vec![0; 8];
+    let sum = AtomicUsize::new(0);
+    for _ in 0..3 {
+        pool.scope(|s| {
+            for (i, value) in values.iter_mut().enumerate() {
+                let (sum, pool) = (&sum, &pool);
+                s.spawn(move || {
+                    *value += i;
+                    sum.fetch_add(i, Ordering::SeqCst);
+                    // a scope inside of a task doesn't starve the pool
+                    pool.scope(|s| s.spawn(|| { sum.fetch_add(1, Ordering::SeqCst); }));
+                });
+            }
+        });
+    }
+    assert!(values == vec![0, 3, 6, 9, 12, 15, 18, 21]);
+    assert!(sum.load(Ordering::SeqCst) == 3 * (28 + 8));
+
+    // a panicking task is reported once the scope ends, and the pool keeps working
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pool.scope(|s| s.spawn(|| panic!("task failed")))));
+    assert!(result.is_err());
+    assert!(pool.scope(|s| { s.spawn(|| ()); 5 }) == 5);
+}
+
+/// Test running systems on a ThreadPool with dispatch_parallel!
+#[test]
+fn thread_pool_dispatch_test(){
+    use genecs::resource::Resource;
+    use genecs::system::{System, Write, Data};
+    use genecs::thread_pool::ThreadPool;
+
+    struct Total(u64);
+    impl_resource!(Total, Total(0));
+
+    struct Add(u64);
+    impl System for Add {
+        type SystemData = Write<Total>;
+        fn run(&mut self, total : Data<Self>) {
+            total.0 += self.0;
+        }
+    }
+
+    let pool = ThreadPool::new(3);
+    let (mut one, mut two, mut three, mut four) = (Add(1), Add(2), Add(3), Add(4));
+    dispatch_parallel!(pool => &mut one, &mut two, &mut three, &mut four);
+    dispatch_parallel!(&mut one, &mut four);
+    let mut guard = acquire!(Read(Total));
+    let (total,) = guard.get();
+    assert!(total.0 == 10 + 5);
+}
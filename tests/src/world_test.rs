@@ -0,0 +1,115 @@
+/// Test that Worlds have their own storages, resources and entities
+#[test]
+fn world_test(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::resource::Resource;
+    use genecs::entity::{Entity, EntityID};
+    use genecs::world::World;
+    use std::collections::BTreeMap;
+
+    impl_component!(Position, BTreeMap<EntityID, Position>);
+    struct Position(u64);
+
+    struct Gravity(u64);
+    impl_resource!(Gravity, Gravity(10));
+
+    let mut first = World::new();
+    let second = World::new();
+    first.insert::<Gravity, _>(Gravity(5));
+
+    let a = first.create_entity();
+    let b = second.create_entity();
+    assert!(a == b, "worlds should allocate entities independently");
+    {
+        let mut guard = acquire!(first => Write(Position), Read(Gravity));
+        let (pos, gravity) = guard.get();
+        pos.component_insert(a, Position(gravity.0));
+    }
+    {
+        let mut guard = acquire!(second => Write(Position), Read(Gravity));
+        let (pos, gravity) = guard.get();
+        assert!(pos.component_get(b).is_none(), "a component leaked between worlds");
+        pos.component_insert(b, Position(gravity.0));
+    }
+    {
+        // the default world is untouched
+        let mut guard = acquire!(Read(Position));
+        let (pos,) = guard.get();
+        assert!(pos.component_get(a).is_none(), "a component leaked into the default world");
+    }
+
+    let mut guard = acquire!(first => Read(Position));
+    let (pos,) = guard.get();
+    assert!(pos.component_get(a).unwrap().0 == 5);
+    drop(guard);
+    let mut guard = acquire!(second => Read(Position));
+    let (pos,) = guard.get();
+    assert!(pos.component_get(b).unwrap().0 == 10);
+    // the entity is checked against the World that created it
+    assert!(Entity::from(b).is_alive_in(second.entities()));
+    assert!(Entity::from(b).get_in(second.entities(), *pos).is_some_and(|p| p.0 == 10));
+    drop(guard);
+
+    assert!(first.despawn(a));
+    assert!(!first.is_alive(a) && second.is_alive(b));
+    assert!(!Entity::from(a).is_alive_in(first.entities()));
+    let mut guard = acquire!(first => Read(Position));
+    let (pos,) = guard.get();
+    assert!(pos.component_get(a).is_none());
+}
+
+/// Test that acquiring the storages of a World blocks while another thread holds them
+#[test]
+fn world_multiple_writes(){
+    use genecs::component::{Component, ComponentStorage};
+    use genecs::entity::EntityID;
+    use genecs::world::World;
+    use std::collections::BTreeMap;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    impl_component!(SimpleTuple, BTreeMap<EntityID, SimpleTuple>);
+    struct SimpleTuple(u64);
+
+    let world = World::new();
+    let id = world.create_entity();
+    let now = Instant::now();
+    thread::scope(|s| {
+        let mut guard = acquire!(world => Write(SimpleTuple));
+        s.spawn(|| {
+            let mut guard = acquire!(world => Write(SimpleTuple));
+            assert!(now.elapsed().as_millis() >= 100);
+            let (st,) = guard.get();
+            st.component_get_mut(id).unwrap().0 += 1;
+        });
+        thread::sleep(Duration::from_millis(100));
+        let (st,) = guard.get();
+        st.component_insert(id, SimpleTuple(1));
+    });
+    let mut guard = acquire!(world => Read(SimpleTuple));
+    let (st,) = guard.get();
+    assert!(st.component_get(id).unwrap().0 == 2);
+}
+
+/// Test that try_acquire! and acquire_timeout! work with the storages of a World
+#[test]
+fn world_try_acquire(){
+    use genecs::component::Component;
+    use genecs::entity::EntityID;
+    use genecs::world::World;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    impl_component!(SimpleTuple, BTreeMap<EntityID, SimpleTuple>);
+    #[allow(dead_code)]
+    struct SimpleTuple(u64);
+
+    let world = World::new();
+    let guard = try_acquire!(world => Read(SimpleTuple)).unwrap();
+    assert!(try_acquire!(world => Write(SimpleTuple)).is_err());
+    assert!(acquire_timeout!(Duration::from_millis(10), world => Write(SimpleTuple)).is_err());
+    // the static storage isn't affected by the World
+    assert!(try_acquire!(Write(SimpleTuple)).is_ok());
+    drop(guard);
+    assert!(acquire_timeout!(Duration::from_millis(10), world => Write(SimpleTuple)).is_ok());
+}